@@ -0,0 +1,71 @@
+#ifndef FCIDR_H
+#define FCIDR_H
+
+/* Warning, this file is autogenerated by cbindgen. Don't modify this manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct Fcidr Fcidr;
+
+// Creates a new empty set. The result must be released with `fcidr_free`.
+Fcidr *fcidr_new(void);
+
+// Releases a set created with `fcidr_new`.
+//
+// # Safety
+//
+// `fcidr` must be null or a pointer returned by `fcidr_new` which has not
+// already been freed.
+void fcidr_free(Fcidr *fcidr);
+
+// Complements the set in place. Returns 0 on success or -1 if `fcidr` is
+// null.
+//
+// # Safety
+//
+// `fcidr` must be null or a valid pointer returned by `fcidr_new`.
+int fcidr_complement(Fcidr *fcidr);
+
+// Removes the nul-terminated CIDR string `cidr` from the set. Returns 0 on
+// success or -1 if either argument is null or `cidr` fails to parse.
+//
+// # Safety
+//
+// `fcidr` must be null or a valid pointer returned by `fcidr_new`, and
+// `cidr` must be null or point to a nul-terminated string.
+int fcidr_difference(Fcidr *fcidr, const char *cidr);
+
+// Adds the nul-terminated CIDR string `cidr` to the set. Returns 0 on success
+// or -1 if either argument is null or `cidr` fails to parse.
+//
+// # Safety
+//
+// `fcidr` must be null or a valid pointer returned by `fcidr_new`, and
+// `cidr` must be null or point to a nul-terminated string.
+int fcidr_union(Fcidr *fcidr, const char *cidr);
+
+// Returns whether the set contains the address `addr`, given in host byte
+// order. A null `fcidr` contains nothing.
+//
+// # Safety
+//
+// `fcidr` must be null or a valid pointer returned by `fcidr_new`.
+bool fcidr_contains(const Fcidr *fcidr, uint32_t addr);
+
+// Invokes `callback` with the network address (in host byte order) and
+// prefix of each block in the set, in ascending order, passing `user_data`
+// through untouched. Returns the number of blocks visited, saturating at
+// `INTPTR_MAX`, or -1 if `fcidr` is null.
+//
+// # Safety
+//
+// `fcidr` must be null or a valid pointer returned by `fcidr_new`, and
+// `callback` must be safe to call with `user_data`.
+intptr_t fcidr_for_each(const Fcidr *fcidr,
+                        void (*callback)(uint32_t network, uint8_t prefix, void *user_data),
+                        void *user_data);
+
+#endif  /* FCIDR_H */
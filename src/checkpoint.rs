@@ -0,0 +1,193 @@
+use std::{collections::VecDeque, ops::Deref};
+
+use crate::{Cidr, Fcidr};
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Undo {
+    Complement,
+    Restore { cidr: Cidr, blocks: Vec<Cidr> },
+}
+
+const DEFAULT_HISTORY_LIMIT: usize = 16;
+
+/// A set whose changes since a checkpoint can be rolled back. Rather than
+/// copies of the set, each checkpoint keeps how to undo the operations made
+/// since, so only what they touched is remembered.
+#[derive(Clone, Debug, Default)]
+pub struct CheckpointedFcidr {
+    fcidr: Fcidr,
+    history: VecDeque<Vec<Undo>>,
+    history_limit: Option<usize>,
+}
+
+impl CheckpointedFcidr {
+    pub fn new(fcidr: Fcidr) -> Self {
+        Self {
+            fcidr,
+            ..Default::default()
+        }
+    }
+
+    pub fn checkpoint(&mut self) -> &mut Self {
+        self.history.push_back(Vec::new());
+        while self.history.len() > self.history_limit() {
+            self.history.pop_front();
+        }
+        self
+    }
+
+    pub fn rollback(&mut self) -> bool {
+        let Some(journal) = self.history.pop_back() else {
+            return false;
+        };
+        for undo in journal.into_iter().rev() {
+            match undo {
+                Undo::Complement => {
+                    self.fcidr.complement();
+                }
+                Undo::Restore { cidr, blocks } => {
+                    self.fcidr.difference(cidr);
+                    for block in blocks {
+                        self.fcidr.union(block);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    pub fn checkpoints(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn history_limit(&self) -> usize {
+        self.history_limit.unwrap_or(DEFAULT_HISTORY_LIMIT)
+    }
+
+    pub fn set_history_limit(&mut self, limit: usize) -> &mut Self {
+        self.history_limit = Some(limit);
+        while self.history.len() > limit {
+            self.history.pop_front();
+        }
+        self
+    }
+
+    /// Remembers what the set covers within the block, which is all an
+    /// operation confined to it can change.
+    fn record(&mut self, cidr: Cidr) {
+        if self.history.is_empty() {
+            return;
+        }
+        let blocks = self.fcidr.covered_within(cidr);
+        if let Some(journal) = self.history.back_mut() {
+            journal.push(Undo::Restore { cidr, blocks });
+        }
+    }
+
+    pub fn complement(&mut self) -> &mut Self {
+        if let Some(journal) = self.history.back_mut() {
+            journal.push(Undo::Complement);
+        }
+        self.fcidr.complement();
+        self
+    }
+
+    pub fn difference(&mut self, cidr: Cidr) -> &mut Self {
+        self.record(cidr);
+        self.fcidr.difference(cidr);
+        self
+    }
+
+    pub fn restrict(&mut self, universe: Cidr) -> &mut Self {
+        // Only what lies outside the universe changes, which is the sibling of
+        // the universe and of each of its ancestors.
+        let mut cidr = universe;
+        while let Some(parent) = cidr.parent() {
+            if let Some([left, right]) = parent.split() {
+                self.record(if left == cidr { right } else { left });
+            }
+            cidr = parent;
+        }
+        self.fcidr.restrict(universe);
+        self
+    }
+
+    pub fn symmetric_difference(&mut self, cidr: Cidr) -> &mut Self {
+        self.record(cidr);
+        self.fcidr.symmetric_difference(cidr);
+        self
+    }
+
+    pub fn union(&mut self, cidr: Cidr) -> &mut Self {
+        self.record(cidr);
+        self.fcidr.union(cidr);
+        self
+    }
+
+    pub fn into_inner(self) -> Fcidr {
+        self.fcidr
+    }
+}
+
+impl Deref for CheckpointedFcidr {
+    type Target = Fcidr;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fcidr
+    }
+}
+
+impl Fcidr {
+    pub fn checkpointed(self) -> CheckpointedFcidr {
+        CheckpointedFcidr::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap()).checkpointed();
+        let before = fcidr.iter().collect::<Vec<_>>();
+        fcidr
+            .checkpoint()
+            .difference("10.0.64.0/20".parse().unwrap())
+            .union("10.0.82.0/24".parse().unwrap())
+            .complement()
+            .symmetric_difference("10.0.82.64/26".parse().unwrap())
+            .union("10.0.82.74/31".parse().unwrap());
+        let after = fcidr.iter().collect::<Vec<_>>();
+        fcidr.checkpoint().union("0.0.0.0/0".parse().unwrap());
+        assert_eq!(fcidr.checkpoints(), 2);
+        assert!(fcidr.rollback());
+        assert_eq!(fcidr.iter().collect::<Vec<_>>(), after);
+        assert!(fcidr.rollback());
+        assert_eq!(fcidr.iter().collect::<Vec<_>>(), before);
+        assert!(!fcidr.rollback());
+        fcidr.set_history_limit(1).checkpoint().checkpoint();
+        assert_eq!(fcidr.checkpoints(), 1);
+    }
+
+    #[test]
+    fn restrict() {
+        let mut fcidr = Fcidr::default().checkpointed();
+        fcidr
+            .union("10.0.0.0/8".parse().unwrap())
+            .union("172.16.0.0/12".parse().unwrap())
+            .difference("10.1.2.0/24".parse().unwrap());
+        let before = fcidr.to_vec();
+        fcidr.checkpoint().restrict("10.1.0.0/16".parse().unwrap());
+        assert_eq!(fcidr.get(0), Some("10.1.0.0/23".parse().unwrap()));
+        let journal = fcidr.history.back().unwrap();
+        assert_eq!(journal.len(), 16);
+        assert!(journal.iter().all(|undo| match undo {
+            Undo::Restore { cidr, .. } => !cidr.overlaps("10.1.0.0/16".parse().unwrap()),
+            Undo::Complement => false,
+        }));
+        assert!(fcidr.rollback());
+        assert_eq!(fcidr.to_vec(), before);
+        assert_eq!(fcidr.into_inner().to_vec(), before);
+    }
+}
@@ -1,17 +1,67 @@
 use std::{
-    fmt::{Debug, Display},
+    fmt::{Binary, Debug, Display},
+    iter::FusedIterator,
     net::Ipv4Addr,
+    ops::RangeInclusive,
     str::FromStr,
 };
 
-use crate::Error;
+use crate::{stream::Aggregate, Error};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Cidr),
+    diesel(sql_type = diesel::sql_types::Inet)
+)]
 pub struct Cidr {
     network: Ipv4Addr,
     prefix: u8,
 }
 
+const fn block(a: u8, b: u8, c: u8, d: u8, prefix: u8) -> Cidr {
+    Cidr {
+        network: Ipv4Addr::new(a, b, c, d),
+        prefix,
+    }
+}
+
+const PRIVATE: &[Cidr] = &[
+    block(10, 0, 0, 0, 8),
+    block(172, 16, 0, 0, 12),
+    block(192, 168, 0, 0, 16),
+];
+const DOCUMENTATION: &[Cidr] = &[
+    block(192, 0, 2, 0, 24),
+    block(198, 51, 100, 0, 24),
+    block(203, 0, 113, 0, 24),
+];
+// The blocks Ipv4Addr::is_global rejects; 192.0.0.0/24 is split around the
+// globally reachable 192.0.0.9 and 192.0.0.10.
+const NOT_GLOBAL: &[Cidr] = &[
+    block(0, 0, 0, 0, 8),
+    block(10, 0, 0, 0, 8),
+    block(100, 64, 0, 0, 10),
+    block(127, 0, 0, 0, 8),
+    block(169, 254, 0, 0, 16),
+    block(172, 16, 0, 0, 12),
+    block(192, 0, 0, 0, 29),
+    block(192, 0, 0, 8, 32),
+    block(192, 0, 0, 11, 32),
+    block(192, 0, 0, 12, 30),
+    block(192, 0, 0, 16, 28),
+    block(192, 0, 0, 32, 27),
+    block(192, 0, 0, 64, 26),
+    block(192, 0, 0, 128, 25),
+    block(192, 0, 2, 0, 24),
+    block(192, 168, 0, 0, 16),
+    block(198, 18, 0, 0, 15),
+    block(198, 51, 100, 0, 24),
+    block(203, 0, 113, 0, 24),
+    block(240, 0, 0, 0, 4),
+];
+
 impl Cidr {
     pub fn new(network: Ipv4Addr, prefix: u8) -> Result<Self, Error> {
         if prefix as u32 > u32::BITS {
@@ -36,6 +86,123 @@ impl Cidr {
         Ok(Self { network, prefix })
     }
 
+    /// Like [`Cidr::new`], but clearing any bits of `network` after the
+    /// prefix instead of rejecting them, so 10.1.2.3/24 becomes 10.1.2.0/24.
+    pub fn new_truncate(network: Ipv4Addr, prefix: u8) -> Result<Self, Error> {
+        if prefix as u32 > u32::BITS {
+            return Err(Error::InvalidPrefix(format!(
+                "network prefix '{prefix}' must be 32 or less"
+            )));
+        }
+        Ok(Self::truncate(network, prefix))
+    }
+
+    /// The `const` counterpart of [`Cidr::new`], for blocks known when
+    /// compiling, e.g. through [`cidr!`](crate::cidr).
+    ///
+    /// # Panics
+    ///
+    /// If the prefix is greater than 32 or the network has bits set after it,
+    /// which fails compilation when evaluated in a constant.
+    pub const fn new_const(network: Ipv4Addr, prefix: u8) -> Self {
+        if prefix as u32 > u32::BITS {
+            panic!("network prefix must be 32 or less");
+        }
+        let bits = u32::from_be_bytes(network.octets());
+        if (prefix as u32) < u32::BITS && bits << prefix != 0 {
+            panic!("network address must be clear after the network prefix");
+        }
+        Self { network, prefix }
+    }
+
+    /// Parses like [`FromStr`], but also accepts a bare address as a /32, for
+    /// lists mixing addresses and blocks.
+    pub fn from_str_lossy(s: &str) -> Result<Self, Error> {
+        match s.parse::<Ipv4Addr>() {
+            Ok(ip) => Ok(ip.into()),
+            Err(_) => s.parse(),
+        }
+    }
+
+    /// Parses like [`Cidr::from_str_lossy`], but clearing host bits as
+    /// [`Cidr::new_truncate`] does.
+    pub fn from_str_truncate(s: &str) -> Result<Self, Error> {
+        match s.parse::<Ipv4Addr>() {
+            Ok(ip) => Ok(ip.into()),
+            Err(_) => Self::parse(s, Self::new_truncate),
+        }
+    }
+
+    /// Creates the block from a dotted-quad netmask such as 255.255.0.0, which
+    /// must be a contiguous run of set bits followed by clear bits.
+    pub fn from_netmask(network: Ipv4Addr, netmask: Ipv4Addr) -> Result<Self, Error> {
+        Self::new(network, Self::netmask_prefix(netmask)?)
+    }
+
+    fn netmask_prefix(netmask: Ipv4Addr) -> Result<u8, Error> {
+        let mask = u32::from(netmask);
+        if mask.leading_ones() + mask.trailing_zeros() != u32::BITS {
+            return Err(Error::InvalidNetmask(format!(
+                "netmask '{netmask}' must be contiguous"
+            )));
+        }
+        Ok(mask.leading_ones() as u8)
+    }
+
+    /// Creates the block from an ACL-style wildcard mask such as 0.0.255.255,
+    /// the inverse of a netmask.
+    pub fn from_wildcard(network: Ipv4Addr, wildcard: Ipv4Addr) -> Result<Self, Error> {
+        Self::from_netmask(network, (!u32::from(wildcard)).into()).map_err(|e| match e {
+            Error::InvalidNetmask(_) => {
+                Error::InvalidNetmask(format!("wildcard mask '{wildcard}' must be contiguous"))
+            }
+            e => e,
+        })
+    }
+
+    pub(crate) fn truncate(network: Ipv4Addr, prefix: u8) -> Self {
+        let shift = u32::BITS - prefix as u32;
+        let network = u32::from(network)
+            .checked_shr(shift)
+            .and_then(|n| n.checked_shl(shift))
+            .unwrap_or_default();
+        Self {
+            network: network.into(),
+            prefix,
+        }
+    }
+
+    /// Returns the fewest CIDRs exactly covering the inclusive address range,
+    /// or none if the range is reversed.
+    pub fn cover_range(first: Ipv4Addr, last: Ipv4Addr) -> Vec<Self> {
+        let (mut first, last) = (u32::from(first) as u64, u32::from(last) as u64);
+        let mut cidrs = Vec::new();
+        while first <= last {
+            let mut prefix = u32::BITS - (first as u32).trailing_zeros().min(u32::BITS);
+            while first + (1 << (u32::BITS - prefix)) - 1 > last {
+                prefix += 1;
+            }
+            cidrs.push(Self {
+                network: (first as u32).into(),
+                prefix: prefix as u8,
+            });
+            first += 1 << (u32::BITS - prefix);
+        }
+        cidrs
+    }
+
+    /// Returns the fewest CIDRs covering exactly the same addresses as the
+    /// given ones, in ascending order, merging any that overlap or adjoin.
+    pub fn aggregate(cidrs: &[Cidr]) -> Vec<Self> {
+        let mut sorted = cidrs.to_vec();
+        sorted.sort();
+        Aggregate::new(sorted).collect()
+    }
+
+    pub(crate) fn size(&self) -> u64 {
+        1 << (u32::BITS - self.prefix as u32)
+    }
+
     pub fn network(&self) -> Ipv4Addr {
         self.network
     }
@@ -70,6 +237,103 @@ impl Cidr {
         Ipv4Addr::from(last)
     }
 
+    /// Returns the mask with the first `prefix` bits set, e.g. 255.255.0.0 for
+    /// a /16.
+    pub fn netmask(&self) -> Ipv4Addr {
+        (!u32::from(self.wildcard_mask())).into()
+    }
+
+    /// Returns the inverse of the netmask, as used by ACLs, e.g. 0.0.255.255
+    /// for a /16.
+    pub fn wildcard_mask(&self) -> Ipv4Addr {
+        u32::MAX
+            .checked_shr(self.prefix as u32)
+            .unwrap_or_default()
+            .into()
+    }
+
+    /// Returns the broadcast address, the last address of the block.
+    pub fn broadcast(&self) -> Ipv4Addr {
+        self.last()
+    }
+
+    /// Returns the address `n` places after the first address of the block, or
+    /// `None` if that falls outside of it.
+    pub fn offset(&self, n: u32) -> Option<Ipv4Addr> {
+        u32::from(self.first())
+            .checked_add(n)
+            .map(Ipv4Addr::from)
+            .filter(|&ip| ip <= self.last())
+    }
+
+    /// Returns how many places after the first address of the block `ip` is, or
+    /// `None` if it is outside of the block.
+    pub fn offset_of(&self, ip: Ipv4Addr) -> Option<u32> {
+        self.contains(ip)
+            .then(|| u32::from(ip) - u32::from(self.first()))
+    }
+
+    /// Returns the address at `index` within the block, the inverse of
+    /// [`Cidr::index_of`]. Same as [`Cidr::offset`].
+    pub fn nth(&self, index: u32) -> Option<Ipv4Addr> {
+        self.offset(index)
+    }
+
+    /// Returns the index of `ip` within the block. Same as [`Cidr::offset_of`].
+    pub fn index_of(&self, ip: Ipv4Addr) -> Option<u32> {
+        self.offset_of(ip)
+    }
+
+    /// Yields the first address of the block and every `stride`th address after
+    /// it. Panics if `stride` is zero.
+    pub fn step_by_addresses(&self, stride: u32) -> impl Iterator<Item = Ipv4Addr> {
+        assert!(stride != 0, "stride must be non-zero");
+        (u32::from(self.first()) as u64..=u32::from(self.last()) as u64)
+            .step_by(stride as usize)
+            .map(|address| Ipv4Addr::from(address as u32))
+    }
+
+    /// Yields the addresses assignable to hosts, which excludes the network
+    /// and broadcast addresses unless the block is a /31 point-to-point link
+    /// (RFC 3021) or a single /32 host.
+    pub fn hosts(&self) -> impl Iterator<Item = Ipv4Addr> {
+        let (mut first, mut last) = (
+            u32::from(self.first()) as u64,
+            u32::from(self.last()) as u64,
+        );
+        if (self.prefix as u32) < u32::BITS - 1 {
+            first += 1;
+            last -= 1;
+        }
+        (first..=last).map(|address| Ipv4Addr::from(address as u32))
+    }
+
+    /// Returns the network bits grouped into octets with a `|` at the prefix
+    /// boundary, e.g. `00001010 0000|0000 00000000 00000000` for 10.0.0.0/12.
+    pub fn to_bits_string(&self) -> String {
+        let mut groups = self
+            .network
+            .octets()
+            .iter()
+            .map(|octet| format!("{octet:08b}"))
+            .collect::<Vec<_>>();
+        let (octet, bit) = (self.prefix as usize / 8, self.prefix as usize % 8);
+        if bit == 0 {
+            groups.insert(octet, "|".to_string());
+        } else {
+            groups[octet].insert(bit, '|');
+        }
+        groups.join(" ")
+    }
+
+    /// Returns the CIDR with zero-padded octets and prefix, e.g.
+    /// `010.000.000.000/08`, so that textual order matches numeric order. This
+    /// is also the alternate (`{:#}`) display format.
+    pub fn to_sortable_string(&self) -> String {
+        let [a, b, c, d] = self.network.octets();
+        format!("{a:03}.{b:03}.{c:03}.{d:03}/{:02}", self.prefix)
+    }
+
     pub fn contains<T>(&self, net: T) -> bool
     where
         T: Copy + Debug + Into<Cidr>,
@@ -78,6 +342,70 @@ impl Cidr {
         cidr.first() >= self.first() && cidr.last() <= self.last()
     }
 
+    fn is_within(&self, blocks: &[Cidr]) -> bool {
+        blocks.iter().any(|block| block.contains(*self))
+    }
+
+    /// Returns whether the whole block is private-use space (RFC 1918).
+    pub fn is_private(&self) -> bool {
+        self.is_within(PRIVATE)
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        self.is_within(&[block(127, 0, 0, 0, 8)])
+    }
+
+    pub fn is_link_local(&self) -> bool {
+        self.is_within(&[block(169, 254, 0, 0, 16)])
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.is_within(&[block(224, 0, 0, 0, 4)])
+    }
+
+    /// Returns whether the whole block is reserved for documentation
+    /// (RFC 5737).
+    pub fn is_documentation(&self) -> bool {
+        self.is_within(DOCUMENTATION)
+    }
+
+    /// Returns whether the whole block is carrier-grade NAT shared address
+    /// space (RFC 6598).
+    pub fn is_shared(&self) -> bool {
+        self.is_within(&[block(100, 64, 0, 0, 10)])
+    }
+
+    /// Returns whether every address of the block is globally reachable, as
+    /// decided by the unstable `Ipv4Addr::is_global`.
+    pub fn is_global(&self) -> bool {
+        !NOT_GLOBAL.iter().any(|block| block.overlaps(*self))
+    }
+
+    /// Returns whether the blocks share any address. Unlike [`Cidr::contains`],
+    /// neither needs to cover the other entirely.
+    pub fn overlaps(&self, other: Cidr) -> bool {
+        self.first() <= other.last() && other.first() <= self.last()
+    }
+
+    /// Returns whether the blocks are disjoint but contiguous, one ending right
+    /// before the other begins.
+    pub fn is_adjacent(&self, other: Cidr) -> bool {
+        let (a, b) = if self.first() <= other.first() {
+            (self, &other)
+        } else {
+            (&other, self)
+        };
+        u32::from(a.last()).checked_add(1) == Some(u32::from(b.first()))
+    }
+
+    /// Merges two sibling halves into their parent, or returns `None` if the
+    /// blocks are not the two halves of the same parent.
+    pub fn try_merge(&self, other: Cidr) -> Option<Cidr> {
+        let parent = self.parent()?;
+        (self.prefix == other.prefix && *self != other && other.parent() == Some(parent))
+            .then_some(parent)
+    }
+
     pub fn parent(&self) -> Option<Cidr> {
         match self.prefix {
             0 => None,
@@ -93,6 +421,17 @@ impl Cidr {
         }
     }
 
+    /// Returns the block with the shorter `prefix` containing this one, or
+    /// `None` if `prefix` is longer than this block's.
+    pub fn supernet(&self, prefix: u8) -> Option<Cidr> {
+        (prefix <= self.prefix).then(|| Self::truncate(self.network, prefix))
+    }
+
+    /// Yields the parent, grandparent and so on up to 0.0.0.0/0.
+    pub fn ancestors(&self) -> impl Iterator<Item = Cidr> {
+        std::iter::successors(self.parent(), Cidr::parent)
+    }
+
     pub fn left_subnet(&self) -> Option<Cidr> {
         match self.prefix as u32 {
             u32::BITS => None,
@@ -111,12 +450,27 @@ impl Cidr {
                 let shift = u32::BITS - prefix as u32;
                 Some(Self {
                     network: (((u32::from(self.network) >> shift) | 1) << shift).into(),
-                    prefix: prefix,
+                    prefix,
                 })
             }
         }
     }
 
+    /// Lazily yields every subnet with the longer `prefix` in address order,
+    /// or none if `prefix` is shorter than this block's or longer than 32.
+    pub fn subnets(&self, prefix: u8) -> impl Iterator<Item = Cidr> {
+        let count = match prefix.checked_sub(self.prefix) {
+            Some(bits) if prefix as u32 <= u32::BITS => 1u64 << bits,
+            _ => 0,
+        };
+        let first = u32::from(self.network) as u64;
+        let shift = u32::BITS.saturating_sub(prefix as u32);
+        (0..count).map(move |i| Cidr {
+            network: ((first + (i << shift)) as u32).into(),
+            prefix,
+        })
+    }
+
     pub fn split(&self) -> Option<[Cidr; 2]> {
         match (self.left_subnet(), self.right_subnet()) {
             (Some(left), Some(right)) => Some([left, right]),
@@ -136,7 +490,17 @@ impl Default for Cidr {
 
 impl Display for Cidr {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}/{}", self.network, self.prefix)
+        if f.alternate() {
+            write!(f, "{}", self.to_sortable_string())
+        } else {
+            write!(f, "{}/{}", self.network, self.prefix)
+        }
+    }
+}
+
+impl Binary for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&self.to_bits_string())
     }
 }
 
@@ -146,6 +510,131 @@ impl From<Ipv4Addr> for Cidr {
     }
 }
 
+impl IntoIterator for Cidr {
+    type Item = Ipv4Addr;
+
+    type IntoIter = CidrIntoIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        CidrIntoIterator {
+            next: u32::from(self.first()) as u64,
+            end: u32::from(self.last()) as u64 + 1,
+        }
+    }
+}
+
+impl IntoIterator for &Cidr {
+    type Item = Ipv4Addr;
+
+    type IntoIter = CidrIntoIterator;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (*self).into_iter()
+    }
+}
+
+/// Yields every address of a [`Cidr`] in ascending order.
+#[derive(Clone, Debug)]
+pub struct CidrIntoIterator {
+    next: u64,
+    end: u64,
+}
+
+impl Iterator for CidrIntoIterator {
+    type Item = Ipv4Addr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.next < self.end).then(|| {
+            self.next += 1;
+            Ipv4Addr::from((self.next - 1) as u32)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // All 2^32 addresses of 0.0.0.0/0 do not fit a 32-bit usize.
+        match usize::try_from(self.end - self.next) {
+            Ok(len) => (len, Some(len)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.next = self.next.saturating_add(n as u64).min(self.end);
+        self.next()
+    }
+}
+
+impl DoubleEndedIterator for CidrIntoIterator {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        (self.next < self.end).then(|| {
+            self.end -= 1;
+            Ipv4Addr::from(self.end as u32)
+        })
+    }
+}
+
+/// Only where a `usize` can hold the length of 0.0.0.0/0.
+#[cfg(target_pointer_width = "64")]
+impl ExactSizeIterator for CidrIntoIterator {}
+
+impl FusedIterator for CidrIntoIterator {}
+
+impl From<Cidr> for RangeInclusive<u32> {
+    fn from(value: Cidr) -> Self {
+        u32::from(value.first())..=u32::from(value.last())
+    }
+}
+
+impl From<Cidr> for RangeInclusive<Ipv4Addr> {
+    fn from(value: Cidr) -> Self {
+        value.first()..=value.last()
+    }
+}
+
+/// Fails unless the range is exactly one block; see [`Cidr::cover_range`] and
+/// [`Fcidr::from_ranges`](crate::Fcidr::from_ranges) for arbitrary ranges.
+impl TryFrom<RangeInclusive<Ipv4Addr>> for Cidr {
+    type Error = Error;
+
+    fn try_from(value: RangeInclusive<Ipv4Addr>) -> Result<Self, Self::Error> {
+        match Self::cover_range(*value.start(), *value.end())[..] {
+            [cidr] => Ok(cidr),
+            _ => Err(Error::InvalidNetwork(format!(
+                "range '{}-{}' must be aligned to a single cidr",
+                value.start(),
+                value.end()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<(u32, u8)> for Cidr {
+    type Error = Error;
+
+    fn try_from((network, prefix): (u32, u8)) -> Result<Self, Self::Error> {
+        Self::new(network.into(), prefix)
+    }
+}
+
+/// Packs the network into the high bits and the prefix into the low 8 bits,
+/// so the integers sort in the same order as the blocks.
+impl From<Cidr> for u64 {
+    fn from(value: Cidr) -> Self {
+        (u32::from(value.network) as u64) << 8 | value.prefix as u64
+    }
+}
+
+impl TryFrom<u64> for Cidr {
+    type Error = Error;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        let network = u32::try_from(value >> 8).map_err(|_| {
+            Error::InvalidNetwork(format!("packed cidr '{value}' must fit in 40 bits"))
+        })?;
+        Self::new(network.into(), value as u8)
+    }
+}
+
 // impl TryFrom<Ipv4Addr> for Cidr {
 //     type Error = Error;
 
@@ -157,81 +646,489 @@ impl From<Ipv4Addr> for Cidr {
 impl FromStr for Cidr {
     type Err = Error;
 
+    /// Parses `network/prefix`, or `network/netmask` and `network netmask`
+    /// with a dotted-quad netmask.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, Self::new)
+    }
+}
+
+impl Cidr {
+    fn parse(s: &str, new: fn(Ipv4Addr, u8) -> Result<Self, Error>) -> Result<Self, Error> {
+        let parse_ip = |ip: &str| {
+            ip.parse::<Ipv4Addr>()
+                .map_err(|e| Error::Parse(e.to_string()))
+        };
         if let Some((network, prefix)) = s.split_once('/') {
-            Self::new(
-                network
-                    .parse::<Ipv4Addr>()
-                    .map_err(|e| Error::Parse(e.to_string()))?,
+            if prefix.contains('.') {
+                return new(parse_ip(network)?, Self::netmask_prefix(parse_ip(prefix)?)?);
+            }
+            new(
+                parse_ip(network)?,
                 prefix
                     .parse::<u8>()
                     .map_err(|e| Error::Parse(e.to_string()))?,
             )
+        } else if let Some((network, netmask)) = s.split_once(char::is_whitespace) {
+            new(
+                parse_ip(network)?,
+                Self::netmask_prefix(parse_ip(netmask.trim_start())?)?,
+            )
         } else {
             Err(Error::Parse("missing network prefix delimiter".to_string()))
         }
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-
-//     // #[test]
-//     // fn does_it_work() {
-//     //     let cidr = Cidr::default();
-//     //     println!("{cidr}");
-//     //     println!("{:?}", cidr.parent());
-//     //     println!("{:?}\n", cidr.split());
-//     //     let cidr: Cidr = "0.0.0.0/0".parse().unwrap();
-//     //     println!("{cidr}");
-//     //     println!("{:?}", cidr.parent());
-//     //     println!("{:?}\n", cidr.split());
-//     //     let cidr: Cidr = "48.0.0.0/4".parse().unwrap();
-//     //     println!("{cidr}");
-//     //     println!("{:?}", cidr.parent());
-//     //     println!("{:?}\n", cidr.split());
-//     //     let cidr: Cidr = "10.0.128.0/25".parse().unwrap();
-//     //     println!("{cidr}");
-//     //     println!("{:?}", cidr.parent());
-//     //     println!("{:?}\n", cidr.split());
-//     //     let cidr: Cidr = "255.255.255.255/32".parse().unwrap();
-//     //     println!("{cidr}");
-//     //     println!("{:?}", cidr.parent());
-//     //     println!("{:?}", cidr.split());
-//     // }
-
-//     // #[test]
-//     // fn cidr_constructor() {
-//     //     for prefix in 0..=32 {
-//     //         println!("{}", Cidr::new(Ipv4Addr::new(0b10000000, 0, 0, 0), prefix).unwrap());
-//     //         println!("{}", Cidr::new(Ipv4Addr::new(0xFF, 0xFF, 0xFF, 0xFF), prefix).unwrap());
-//     //     }
-//     // }
-
-//     // #[test]
-//     // fn cidr_first() {
-//     //     let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
-//     //     println!("{} / {} : {} -> {}", cidr.network(), cidr.prefix(), cidr.first(), cidr.last());
-//     //     let cidr: Cidr = "10.0.0.0/9".parse().unwrap();
-//     //     println!("{} / {} : {} -> {}", cidr.network(), cidr.prefix(), cidr.first(), cidr.last());
-//     //     let cidr: Cidr = "10.128.0.0/9".parse().unwrap();
-//     //     println!("{} / {} : {} -> {}", cidr.network(), cidr.prefix(), cidr.first(), cidr.last());
-//     //     let cidr: Cidr = "10.128.0.0/8".parse().unwrap();
-//     //     println!("{} / {} : {} -> {}", cidr.network(), cidr.prefix(), cidr.first(), cidr.last());
-//     // }
-
-//     // #[test]
-//     // fn it_works() {
-//     //     let c: Cidr = "10.0.0.0/8".parse().unwrap();
-//     //     let [l, r] = c.split().unwrap();
-//     //     println!("{l}, {r}");
-//     //     for i in 0..=32 {
-//     //         println!("{} {}", i / 8, i % 8);
-//     //     }
-//     //     let o = 127_u8;
-//     //     println!("{}", o == o >> 1 << 1);
-//     //     println!("{}", "127.0.343.0".parse::<Ipv4Addr>().unwrap());
-//     //     println!("{}", "127.0.343.0".parse::<Cidr>().unwrap());
-//     // }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregate() {
+        let cidrs = [
+            "10.0.1.0/24",
+            "10.0.0.0/24",
+            "10.0.2.0/23",
+            "10.0.2.128/25",
+            "192.168.0.0/24",
+            "10.0.4.0/24",
+            "10.0.0.0/25",
+        ]
+        .iter()
+        .map(|cidr| cidr.parse().unwrap())
+        .collect::<Vec<Cidr>>();
+        assert_eq!(
+            Cidr::aggregate(&cidrs)
+                .iter()
+                .map(Cidr::to_string)
+                .collect::<Vec<_>>(),
+            ["10.0.0.0/22", "10.0.4.0/24", "192.168.0.0/24"]
+        );
+        assert!(Cidr::aggregate(&[]).is_empty());
+    }
+
+    #[test]
+    fn classification() {
+        let parse = |cidr: &str| cidr.parse::<Cidr>().unwrap();
+        assert!(parse("172.20.0.0/16").is_private());
+        assert!(!parse("172.0.0.0/8").is_private());
+        assert!(parse("127.0.0.1/32").is_loopback());
+        assert!(parse("169.254.1.0/24").is_link_local());
+        assert!(parse("239.0.0.0/8").is_multicast());
+        assert!(parse("198.51.100.0/25").is_documentation());
+        assert!(!parse("198.51.0.0/16").is_documentation());
+        assert!(parse("100.64.0.0/10").is_shared());
+        assert!(!parse("100.0.0.0/8").is_shared());
+        assert!(parse("8.8.8.0/24").is_global());
+        assert!(parse("192.0.0.9/32").is_global());
+        assert!(!parse("192.0.0.8/31").is_global());
+        assert!(parse("16.0.0.0/4").is_global());
+        assert!(!parse("96.0.0.0/3").is_global());
+        assert!(!Cidr::default().is_global());
+        assert!(!parse("255.255.255.255/32").is_global());
+    }
+
+    #[test]
+    fn overlaps() {
+        let cidr: Cidr = "10.0.0.0/24".parse().unwrap();
+        assert!(cidr.overlaps(cidr));
+        assert!(cidr.overlaps("10.0.0.128/25".parse().unwrap()));
+        assert!(cidr.overlaps("10.0.0.0/8".parse().unwrap()));
+        assert!(cidr.overlaps(Cidr::default()));
+        assert!(!cidr.overlaps("10.0.1.0/24".parse().unwrap()));
+        assert!(!cidr.overlaps("9.255.255.255/32".parse().unwrap()));
+    }
+
+    #[test]
+    fn try_merge() {
+        let left: Cidr = "10.0.0.0/25".parse().unwrap();
+        let right: Cidr = "10.0.0.128/25".parse().unwrap();
+        let next: Cidr = "10.0.1.0/25".parse().unwrap();
+        assert!(left.is_adjacent(right));
+        assert!(right.is_adjacent(left));
+        assert!(right.is_adjacent(next));
+        assert!(!left.is_adjacent(next));
+        assert!(!left.is_adjacent(left));
+        assert!(!Cidr::default().is_adjacent(Cidr::default()));
+        assert_eq!(left.try_merge(right), Some("10.0.0.0/24".parse().unwrap()));
+        assert_eq!(right.try_merge(left), Some("10.0.0.0/24".parse().unwrap()));
+        assert_eq!(right.try_merge(next), None);
+        assert_eq!(left.try_merge(left), None);
+        assert_eq!(left.try_merge("10.0.0.128/26".parse().unwrap()), None);
+        assert_eq!(Cidr::default().try_merge(Cidr::default()), None);
+    }
+
+    #[test]
+    fn supernet() {
+        let cidr: Cidr = "10.1.2.128/25".parse().unwrap();
+        assert_eq!(cidr.supernet(16), Some("10.1.0.0/16".parse().unwrap()));
+        assert_eq!(cidr.supernet(24), cidr.parent());
+        assert_eq!(cidr.supernet(25), Some(cidr));
+        assert_eq!(cidr.supernet(26), None);
+        assert_eq!(cidr.supernet(0), Some(Cidr::default()));
+    }
+
+    #[test]
+    fn hosts() {
+        let hosts = |cidr: &str| {
+            cidr.parse::<Cidr>()
+                .unwrap()
+                .hosts()
+                .map(|ip| ip.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(hosts("10.0.0.0/30"), ["10.0.0.1", "10.0.0.2"]);
+        assert_eq!(hosts("10.0.0.0/31"), ["10.0.0.0", "10.0.0.1"]);
+        assert_eq!(hosts("10.0.0.7/32"), ["10.0.0.7"]);
+        assert_eq!(hosts("192.168.1.0/24").len(), 254);
+        let mut all = Cidr::default().hosts();
+        assert_eq!(all.next(), Some(Ipv4Addr::new(0, 0, 0, 1)));
+        assert_eq!(all.size_hint().0 as u64, (1u64 << 32) - 3);
+    }
+
+    #[test]
+    fn into_iter() {
+        let cidr: Cidr = "10.0.0.4/30".parse().unwrap();
+        let mut addresses = Vec::new();
+        for ip in cidr {
+            addresses.push(ip.to_string());
+        }
+        assert_eq!(addresses, ["10.0.0.4", "10.0.0.5", "10.0.0.6", "10.0.0.7"]);
+        let mut iter = (&cidr).into_iter();
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+        assert_eq!(iter.next_back(), Some(Ipv4Addr::new(10, 0, 0, 7)));
+        assert_eq!(iter.nth(1), Some(Ipv4Addr::new(10, 0, 0, 5)));
+        assert_eq!(iter.size_hint(), (1, Some(1)));
+        assert_eq!(iter.nth(5), None);
+        assert_eq!(iter.next(), None);
+        let mut all = Cidr::default().into_iter();
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(all.len() as u64, 1 << 32);
+        #[cfg(not(target_pointer_width = "64"))]
+        assert_eq!(all.size_hint(), (usize::MAX, None));
+        assert_eq!(all.next_back(), Some(Ipv4Addr::BROADCAST));
+        assert_eq!(
+            all.nth(u32::MAX as usize - 1),
+            Some(Ipv4Addr::new(255, 255, 255, 254))
+        );
+        assert_eq!(all.next(), None);
+    }
+
+    #[test]
+    fn subnets() {
+        let cidr: Cidr = "10.0.0.0/16".parse().unwrap();
+        let subnets = cidr.subnets(24).collect::<Vec<_>>();
+        assert_eq!(subnets.len(), 256);
+        assert_eq!(subnets[0], "10.0.0.0/24".parse().unwrap());
+        assert_eq!(subnets[255], "10.0.255.0/24".parse().unwrap());
+        assert_eq!(cidr.subnets(16).collect::<Vec<_>>(), [cidr]);
+        assert_eq!(cidr.subnets(15).count(), 0);
+        assert_eq!(cidr.subnets(33).count(), 0);
+        assert_eq!(
+            Cidr::default().subnets(32).size_hint(),
+            (1 << 32, Some(1 << 32))
+        );
+        assert_eq!(
+            "255.255.255.0/24"
+                .parse::<Cidr>()
+                .unwrap()
+                .subnets(32)
+                .last(),
+            Some(Cidr::from(Ipv4Addr::BROADCAST))
+        );
+        assert_eq!(
+            Cidr::default().subnets(1).collect::<Vec<_>>(),
+            Cidr::default().split().unwrap()
+        );
+    }
+
+    #[test]
+    fn cover_range() {
+        let cover = |first: &str, last: &str| {
+            Cidr::cover_range(first.parse().unwrap(), last.parse().unwrap())
+                .iter()
+                .map(Cidr::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(cover("10.0.0.0", "10.0.255.255"), ["10.0.0.0/16"]);
+        assert_eq!(
+            cover("10.0.0.1", "10.0.0.6"),
+            ["10.0.0.1/32", "10.0.0.2/31", "10.0.0.4/31", "10.0.0.6/32"]
+        );
+        assert_eq!(cover("0.0.0.0", "255.255.255.255"), ["0.0.0.0/0"]);
+        assert_eq!(
+            cover("255.255.255.255", "255.255.255.255"),
+            ["255.255.255.255/32"]
+        );
+        assert!(cover("10.0.0.1", "10.0.0.0").is_empty());
+    }
+
+    #[test]
+    fn from_str_lossy() {
+        assert_eq!(
+            Cidr::from_str_lossy("10.1.2.3").unwrap(),
+            "10.1.2.3/32".parse().unwrap()
+        );
+        assert_eq!(
+            Cidr::from_str_lossy("10.1.0.0/16").unwrap(),
+            "10.1.0.0/16".parse().unwrap()
+        );
+        assert!(matches!("10.1.2.3".parse::<Cidr>(), Err(Error::Parse(_))));
+        assert!(matches!(
+            Cidr::from_str_lossy("10.1.2"),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn new_truncate() {
+        assert_eq!(
+            Cidr::new_truncate(Ipv4Addr::new(10, 1, 2, 3), 24).unwrap(),
+            "10.1.2.0/24".parse().unwrap()
+        );
+        assert_eq!(
+            Cidr::new_truncate(Ipv4Addr::BROADCAST, 0).unwrap(),
+            Cidr::default()
+        );
+        assert!(matches!(
+            Cidr::new_truncate(Ipv4Addr::BROADCAST, 33),
+            Err(Error::InvalidPrefix(_))
+        ));
+        for s in ["10.1.2.3/8", "10.1.2.3/255.0.0.0", "10.1.2.3 255.0.0.0"] {
+            assert_eq!(
+                Cidr::from_str_truncate(s).unwrap(),
+                "10.0.0.0/8".parse().unwrap()
+            );
+        }
+        assert_eq!(
+            Cidr::from_str_truncate("10.1.2.3").unwrap(),
+            "10.1.2.3/32".parse().unwrap()
+        );
+        assert!(matches!(
+            Cidr::from_str_truncate("10.1.2.3/255.0.255.0"),
+            Err(Error::InvalidNetmask(_))
+        ));
+    }
+
+    #[test]
+    fn ranges() {
+        let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+        let range = RangeInclusive::<Ipv4Addr>::from(cidr);
+        assert_eq!(
+            range,
+            Ipv4Addr::new(10, 0, 0, 0)..=Ipv4Addr::new(10, 255, 255, 255)
+        );
+        assert_eq!(Cidr::try_from(range).unwrap(), cidr);
+        assert_eq!(
+            Cidr::try_from(Ipv4Addr::UNSPECIFIED..=Ipv4Addr::BROADCAST).unwrap(),
+            Cidr::default()
+        );
+        for range in [
+            Ipv4Addr::new(10, 0, 0, 1)..=Ipv4Addr::new(10, 255, 255, 255),
+            Ipv4Addr::new(10, 0, 0, 0)..=Ipv4Addr::new(10, 0, 0, 2),
+            Ipv4Addr::new(10, 0, 0, 1)..=Ipv4Addr::new(10, 0, 0, 0),
+        ] {
+            assert!(matches!(
+                Cidr::try_from(range),
+                Err(Error::InvalidNetwork(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn integers() {
+        let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(RangeInclusive::<u32>::from(cidr), 0x0a000000..=0x0affffff);
+        assert_eq!(Cidr::try_from((0x0a000000, 8)).unwrap(), cidr);
+        assert!(matches!(
+            Cidr::try_from((0x0a000001, 8)),
+            Err(Error::InvalidNetwork(_))
+        ));
+        assert_eq!(u64::from(cidr), 0x0a00000008);
+        for cidr in [cidr, Cidr::default(), Ipv4Addr::BROADCAST.into()] {
+            assert_eq!(Cidr::try_from(u64::from(cidr)).unwrap(), cidr);
+        }
+        let mut cidrs =
+            ["10.0.0.0/16", "10.0.0.0/8", "9.0.0.0/8"].map(|cidr| cidr.parse::<Cidr>().unwrap());
+        let mut packed = cidrs.map(u64::from);
+        cidrs.sort();
+        packed.sort();
+        assert_eq!(packed, cidrs.map(u64::from));
+        assert!(matches!(
+            Cidr::try_from(0x0a00000021),
+            Err(Error::InvalidPrefix(_))
+        ));
+        assert!(matches!(
+            Cidr::try_from(1 << 40),
+            Err(Error::InvalidNetwork(_))
+        ));
+    }
+
+    #[test]
+    fn from_netmask() {
+        let cidr: Cidr = "10.1.0.0/16".parse().unwrap();
+        assert_eq!("10.1.0.0/255.255.0.0".parse::<Cidr>().unwrap(), cidr);
+        assert_eq!("10.1.0.0 255.255.0.0".parse::<Cidr>().unwrap(), cidr);
+        assert_eq!("10.1.0.0 \t255.255.0.0".parse::<Cidr>().unwrap(), cidr);
+        assert_eq!(
+            Cidr::from_netmask(Ipv4Addr::new(10, 1, 0, 0), cidr.netmask()).unwrap(),
+            cidr
+        );
+        assert_eq!("0.0.0.0 0.0.0.0".parse::<Cidr>().unwrap(), Cidr::default());
+        assert_eq!(
+            "1.2.3.4/255.255.255.255".parse::<Cidr>().unwrap(),
+            Cidr::from(Ipv4Addr::new(1, 2, 3, 4))
+        );
+        assert!(matches!(
+            "10.0.0.0/255.0.255.0".parse::<Cidr>(),
+            Err(Error::InvalidNetmask(_))
+        ));
+        assert!(matches!(
+            "10.0.0.0 0.0.255.255".parse::<Cidr>(),
+            Err(Error::InvalidNetmask(_))
+        ));
+        assert!(matches!(
+            "10.1.0.0 255.0.0.0".parse::<Cidr>(),
+            Err(Error::InvalidNetwork(_))
+        ));
+        assert!(matches!(
+            "10.0.0.0/255.255.0".parse::<Cidr>(),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn from_wildcard() {
+        let network = Ipv4Addr::new(10, 0, 0, 0);
+        assert_eq!(
+            Cidr::from_wildcard(network, Ipv4Addr::new(0, 0, 255, 255)).unwrap(),
+            "10.0.0.0/16".parse().unwrap()
+        );
+        assert_eq!(
+            Cidr::from_wildcard(network, Ipv4Addr::UNSPECIFIED).unwrap(),
+            Cidr::from(network)
+        );
+        assert_eq!(
+            Cidr::from_wildcard(Ipv4Addr::UNSPECIFIED, Ipv4Addr::BROADCAST).unwrap(),
+            Cidr::default()
+        );
+        let cidr = "192.168.4.0/22".parse::<Cidr>().unwrap();
+        assert_eq!(
+            Cidr::from_wildcard(cidr.network(), cidr.wildcard_mask()).unwrap(),
+            cidr
+        );
+        match Cidr::from_wildcard(network, Ipv4Addr::new(0, 255, 0, 255)) {
+            Err(Error::InvalidNetmask(message)) => assert!(message.contains("wildcard")),
+            result => panic!("{result:?}"),
+        }
+        assert!(Cidr::from_wildcard(network, Ipv4Addr::new(0, 255, 255, 255)).is_ok());
+        assert!(matches!(
+            Cidr::from_wildcard(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(0, 0, 0, 255)),
+            Err(Error::InvalidNetwork(_))
+        ));
+    }
+
+    #[test]
+    fn masks() {
+        let cidr: Cidr = "10.1.0.0/16".parse().unwrap();
+        assert_eq!(cidr.netmask(), Ipv4Addr::new(255, 255, 0, 0));
+        assert_eq!(cidr.wildcard_mask(), Ipv4Addr::new(0, 0, 255, 255));
+        assert_eq!(cidr.broadcast(), Ipv4Addr::new(10, 1, 255, 255));
+        let cidr: Cidr = "192.168.1.128/25".parse().unwrap();
+        assert_eq!(cidr.netmask(), Ipv4Addr::new(255, 255, 255, 128));
+        assert_eq!(cidr.wildcard_mask(), Ipv4Addr::new(0, 0, 0, 127));
+        assert_eq!(cidr.broadcast(), Ipv4Addr::new(192, 168, 1, 255));
+        assert_eq!(Cidr::default().netmask(), Ipv4Addr::UNSPECIFIED);
+        assert_eq!(Cidr::default().wildcard_mask(), Ipv4Addr::BROADCAST);
+        let host = Cidr::from(Ipv4Addr::new(1, 2, 3, 4));
+        assert_eq!(host.netmask(), Ipv4Addr::BROADCAST);
+        assert_eq!(host.wildcard_mask(), Ipv4Addr::UNSPECIFIED);
+        assert_eq!(host.broadcast(), Ipv4Addr::new(1, 2, 3, 4));
+    }
+
+    #[test]
+    fn offset() {
+        let cidr: Cidr = "10.0.0.0/24".parse().unwrap();
+        assert_eq!(cidr.offset(0), Some(Ipv4Addr::new(10, 0, 0, 0)));
+        assert_eq!(cidr.offset(255), Some(Ipv4Addr::new(10, 0, 0, 255)));
+        assert_eq!(cidr.offset(256), None);
+        assert_eq!(cidr.offset_of(Ipv4Addr::new(10, 0, 0, 42)), Some(42));
+        assert_eq!(cidr.offset_of(Ipv4Addr::new(10, 0, 1, 0)), None);
+        assert_eq!(cidr.nth(7), Some(Ipv4Addr::new(10, 0, 0, 7)));
+        assert_eq!(cidr.index_of(Ipv4Addr::new(10, 0, 0, 7)), Some(7));
+        assert_eq!(cidr.nth(256), None);
+        let cidr: Cidr = "255.255.255.0/24".parse().unwrap();
+        assert_eq!(cidr.offset(u32::MAX), None);
+        assert_eq!(Cidr::default().offset(u32::MAX), Some(Ipv4Addr::BROADCAST));
+        assert_eq!(
+            Cidr::default().offset_of(Ipv4Addr::BROADCAST),
+            Some(u32::MAX)
+        );
+    }
+
+    #[test]
+    fn to_bits_string() {
+        let cidr: Cidr = "10.0.0.0/16".parse().unwrap();
+        assert_eq!(format!("{cidr:b}"), "00001010 00000000 | 00000000 00000000");
+        let cidr: Cidr = "172.16.0.0/12".parse().unwrap();
+        assert_eq!(
+            cidr.to_bits_string(),
+            "10101100 0001|0000 00000000 00000000"
+        );
+        assert!(Cidr::default().to_bits_string().starts_with("| "));
+        let cidr: Cidr = "255.255.255.255/32".parse().unwrap();
+        assert!(cidr.to_bits_string().ends_with("11111111 |"));
+    }
+
+    #[test]
+    fn ancestors() {
+        let cidr: Cidr = "10.0.0.0/10".parse().unwrap();
+        assert_eq!(cidr.ancestors().count(), 10);
+        assert_eq!(cidr.ancestors().next(), Some("10.0.0.0/9".parse().unwrap()));
+        assert_eq!(cidr.ancestors().nth(3), Some("8.0.0.0/6".parse().unwrap()));
+        assert_eq!(cidr.ancestors().last(), Some(Cidr::default()));
+        assert_eq!(Cidr::default().ancestors().next(), None);
+    }
+
+    #[test]
+    fn to_sortable_string() {
+        let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(cidr.to_sortable_string(), "010.000.000.000/08");
+        assert_eq!(format!("{cidr:#}"), "010.000.000.000/08");
+        assert_eq!(format!("{cidr}"), "10.0.0.0/8");
+        let mut cidrs = ["9.0.0.0/8", "10.0.0.0/8", "10.0.0.0/16", "100.64.0.0/10"]
+            .map(|c| c.parse::<Cidr>().unwrap().to_sortable_string());
+        cidrs.sort();
+        assert_eq!(
+            cidrs,
+            [
+                "009.000.000.000/08",
+                "010.000.000.000/08",
+                "010.000.000.000/16",
+                "100.064.000.000/10",
+            ]
+        );
+    }
+
+    #[test]
+    fn step_by_addresses() {
+        let cidr: Cidr = "10.0.0.0/24".parse().unwrap();
+        assert_eq!(
+            cidr.step_by_addresses(64).collect::<Vec<_>>(),
+            vec![
+                Ipv4Addr::new(10, 0, 0, 0),
+                Ipv4Addr::new(10, 0, 0, 64),
+                Ipv4Addr::new(10, 0, 0, 128),
+                Ipv4Addr::new(10, 0, 0, 192),
+            ]
+        );
+        assert_eq!(cidr.step_by_addresses(1000).count(), 1);
+        assert_eq!(
+            Cidr::default()
+                .step_by_addresses(u32::MAX)
+                .collect::<Vec<_>>(),
+            vec![Ipv4Addr::UNSPECIFIED, Ipv4Addr::BROADCAST]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use std::{fmt::Display, net::Ipv6Addr, str::FromStr};
+
+use crate::Error;
+
+/// The IPv6 counterpart of [`Cidr`](crate::Cidr), such as `2001:db8::/32`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Cidr6 {
+    network: Ipv6Addr,
+    prefix: u8,
+}
+
+impl Cidr6 {
+    pub fn new(network: Ipv6Addr, prefix: u8) -> Result<Self, Error> {
+        if prefix as u32 > u128::BITS {
+            return Err(Error::InvalidPrefix(format!(
+                "network prefix '{prefix}' must be 128 or less"
+            )));
+        }
+        if u128::from(network) & Self::host_mask(prefix) != 0 {
+            return Err(Error::InvalidNetwork(format!(
+                "network address '{network}' must be clear after the first {prefix} bits"
+            )));
+        }
+        Ok(Self { network, prefix })
+    }
+
+    /// Builds the block without checking it, for constants.
+    pub(crate) const fn from_parts(network: Ipv6Addr, prefix: u8) -> Self {
+        Self { network, prefix }
+    }
+
+    /// Like [`Cidr6::new`], but clearing any bits of `network` after the
+    /// prefix instead of rejecting them.
+    pub fn new_truncate(network: Ipv6Addr, prefix: u8) -> Result<Self, Error> {
+        if prefix as u32 > u128::BITS {
+            return Err(Error::InvalidPrefix(format!(
+                "network prefix '{prefix}' must be 128 or less"
+            )));
+        }
+        Ok(Self::truncate(network, prefix))
+    }
+
+    /// Parses like [`FromStr`], but also accepts a bare address as a /128.
+    pub fn from_str_lossy(s: &str) -> Result<Self, Error> {
+        match s.parse::<Ipv6Addr>() {
+            Ok(ip) => Ok(ip.into()),
+            Err(_) => s.parse(),
+        }
+    }
+
+    /// Parses like [`Cidr6::from_str_lossy`], but clearing host bits as
+    /// [`Cidr6::new_truncate`] does.
+    pub fn from_str_truncate(s: &str) -> Result<Self, Error> {
+        match s.parse::<Ipv6Addr>() {
+            Ok(ip) => Ok(ip.into()),
+            Err(_) => Self::parse(s, Self::new_truncate),
+        }
+    }
+
+    pub(crate) fn truncate(network: Ipv6Addr, prefix: u8) -> Self {
+        Self {
+            network: (u128::from(network) & !Self::host_mask(prefix)).into(),
+            prefix,
+        }
+    }
+
+    fn host_mask(prefix: u8) -> u128 {
+        u128::MAX.checked_shr(prefix as u32).unwrap_or_default()
+    }
+
+    pub fn network(&self) -> Ipv6Addr {
+        self.network
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn first(&self) -> Ipv6Addr {
+        self.network
+    }
+
+    pub fn mid(&self) -> Ipv6Addr {
+        if self.prefix as u32 == u128::BITS {
+            self.network
+        } else {
+            (u128::from(self.network) | (1 << (u128::BITS - self.prefix as u32 - 1))).into()
+        }
+    }
+
+    pub fn last(&self) -> Ipv6Addr {
+        (u128::from(self.network) | Self::host_mask(self.prefix)).into()
+    }
+
+    pub fn contains<T>(&self, net: T) -> bool
+    where
+        T: Into<Cidr6>,
+    {
+        let cidr: Cidr6 = net.into();
+        cidr.first() >= self.first() && cidr.last() <= self.last()
+    }
+
+    pub fn parent(&self) -> Option<Cidr6> {
+        self.prefix
+            .checked_sub(1)
+            .map(|prefix| Self::truncate(self.network, prefix))
+    }
+
+    /// Yields the parent, grandparent and so on up to ::/0.
+    pub fn ancestors(&self) -> impl Iterator<Item = Cidr6> {
+        std::iter::successors(self.parent(), Cidr6::parent)
+    }
+
+    pub fn left_subnet(&self) -> Option<Cidr6> {
+        match self.prefix as u32 {
+            u128::BITS => None,
+            _ => Some(Self {
+                network: self.network,
+                prefix: self.prefix + 1,
+            }),
+        }
+    }
+
+    pub fn right_subnet(&self) -> Option<Cidr6> {
+        match self.prefix as u32 {
+            u128::BITS => None,
+            _ => Some(Self {
+                network: self.mid(),
+                prefix: self.prefix + 1,
+            }),
+        }
+    }
+
+    pub fn split(&self) -> Option<[Cidr6; 2]> {
+        match (self.left_subnet(), self.right_subnet()) {
+            (Some(left), Some(right)) => Some([left, right]),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Cidr6 {
+    fn default() -> Self {
+        Self {
+            network: Ipv6Addr::UNSPECIFIED,
+            prefix: Default::default(),
+        }
+    }
+}
+
+impl Display for Cidr6 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+impl From<Ipv6Addr> for Cidr6 {
+    fn from(value: Ipv6Addr) -> Self {
+        Self::new(value, u128::BITS as u8).expect("convert from Ipv6Addr")
+    }
+}
+
+impl FromStr for Cidr6 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, Self::new)
+    }
+}
+
+impl Cidr6 {
+    fn parse(s: &str, new: fn(Ipv6Addr, u8) -> Result<Self, Error>) -> Result<Self, Error> {
+        if let Some((network, prefix)) = s.split_once('/') {
+            new(
+                network
+                    .parse::<Ipv6Addr>()
+                    .map_err(|e| Error::Parse(e.to_string()))?,
+                prefix
+                    .parse::<u8>()
+                    .map_err(|e| Error::Parse(e.to_string()))?,
+            )
+        } else {
+            Err(Error::Parse("missing network prefix delimiter".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let cidr: Cidr6 = "2001:db8::/32".parse().unwrap();
+        assert_eq!(cidr.to_string(), "2001:db8::/32");
+        assert_eq!(
+            cidr.last(),
+            "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"
+                .parse::<Ipv6Addr>()
+                .unwrap()
+        );
+        let [left, right] = cidr.split().unwrap();
+        assert_eq!(left.to_string(), "2001:db8::/33");
+        assert_eq!(right.to_string(), "2001:db8:8000::/33");
+        assert_eq!(right.parent(), Some(cidr));
+        assert_eq!(cidr.parent().unwrap().to_string(), "2001:db8::/31");
+        assert_eq!(cidr.ancestors().count(), 32);
+        assert_eq!(cidr.ancestors().last(), Some(Cidr6::default()));
+        assert!(cidr.contains(right));
+        assert!(cidr.contains("2001:db8:1::1".parse::<Ipv6Addr>().unwrap()));
+        assert!(!left.contains(right));
+        let host = Cidr6::from(Ipv6Addr::LOCALHOST);
+        assert_eq!(host.split(), None);
+        assert_eq!(host.to_string(), "::1/128");
+        assert!(matches!(
+            "2001:db8::/129".parse::<Cidr6>(),
+            Err(Error::InvalidPrefix(_))
+        ));
+        assert!(matches!(
+            "2001:db8::1/64".parse::<Cidr6>(),
+            Err(Error::InvalidNetwork(_))
+        ));
+        assert!(matches!(
+            "10.0.0.0/8".parse::<Cidr6>(),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            "2001:db8::".parse::<Cidr6>(),
+            Err(Error::Parse(_))
+        ));
+        assert_eq!(
+            Cidr6::from_str_lossy("2001:db8::").unwrap().to_string(),
+            "2001:db8::/128"
+        );
+        assert_eq!(Cidr6::from_str_truncate("2001:db8::1/32").unwrap(), cidr);
+        assert!(matches!(
+            Cidr6::new_truncate(Ipv6Addr::LOCALHOST, 129),
+            Err(Error::InvalidPrefix(_))
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "cidr-crate")]
+#![cfg_attr(doc_cfg, doc(cfg(feature = "cidr-crate")))]
+
+use ::cidr::{Ipv4Cidr, Ipv4Inet};
+
+use crate::Cidr;
+
+impl From<Cidr> for Ipv4Cidr {
+    fn from(value: Cidr) -> Self {
+        Self::new(value.network(), value.prefix()).expect("convert from Cidr")
+    }
+}
+
+impl From<Ipv4Cidr> for Cidr {
+    fn from(value: Ipv4Cidr) -> Self {
+        Self::new(value.first_address(), value.network_length()).expect("convert from Ipv4Cidr")
+    }
+}
+
+impl From<Cidr> for Ipv4Inet {
+    fn from(value: Cidr) -> Self {
+        Self::new(value.network(), value.prefix()).expect("convert from Cidr")
+    }
+}
+
+impl From<Ipv4Inet> for Cidr {
+    fn from(value: Ipv4Inet) -> Self {
+        value.network().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let cidr: Cidr = "10.128.0.0/9".parse().unwrap();
+        let ipv4_cidr = Ipv4Cidr::from(cidr);
+        assert_eq!(ipv4_cidr.to_string(), "10.128.0.0/9");
+        assert_eq!(Cidr::from(ipv4_cidr), cidr);
+        let ipv4_inet = Ipv4Inet::from(cidr);
+        assert_eq!(ipv4_inet.address(), cidr.network());
+        assert_eq!(Cidr::from(ipv4_inet), cidr);
+        let ipv4_inet: Ipv4Inet = "10.130.1.2/9".parse().unwrap();
+        assert_eq!(Cidr::from(ipv4_inet), cidr);
+    }
+}
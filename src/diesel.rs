@@ -0,0 +1,84 @@
+#![cfg(feature = "diesel")]
+#![cfg_attr(doc_cfg, doc(cfg(feature = "diesel")))]
+
+use std::{io::Write, net::Ipv4Addr};
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types,
+};
+
+use crate::Cidr;
+
+// https://github.com/postgres/postgres/blob/REL_16_0/src/include/utils/inet.h#L33
+const PGSQL_AF_INET: u8 = 2;
+
+fn from_sql(value: PgValue<'_>, is_cidr: u8) -> deserialize::Result<Cidr> {
+    from_bytes(value.as_bytes(), is_cidr)
+}
+
+/// Decodes the binary form of a `cidr` or `inet` value. An `inet` may have
+/// host bits set, as in `10.1.2.3/24`, which are cleared.
+fn from_bytes(bytes: &[u8], is_cidr: u8) -> deserialize::Result<Cidr> {
+    match bytes {
+        [PGSQL_AF_INET, prefix, 1, 4, a, b, c, d] if is_cidr == 1 => {
+            Ok(Cidr::new(Ipv4Addr::new(*a, *b, *c, *d), *prefix)?)
+        }
+        [PGSQL_AF_INET, prefix, 0, 4, a, b, c, d] if is_cidr == 0 => {
+            Ok(Cidr::new_truncate(Ipv4Addr::new(*a, *b, *c, *d), *prefix)?)
+        }
+        [family, ..] if *family != PGSQL_AF_INET => {
+            Err(format!("unsupported ip family {family}").into())
+        }
+        _ => Err("invalid network address format".into()),
+    }
+}
+
+fn to_sql(cidr: &Cidr, out: &mut Output<'_, '_, Pg>, is_cidr: u8) -> serialize::Result {
+    // https://github.com/postgres/postgres/blob/REL_16_0/src/backend/utils/adt/network.c#L270
+    let [a, b, c, d] = cidr.network().octets();
+    out.write_all(&[PGSQL_AF_INET, cidr.prefix(), is_cidr, 4, a, b, c, d])?;
+    Ok(IsNull::No)
+}
+
+impl FromSql<sql_types::Cidr, Pg> for Cidr {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        from_sql(value, 1)
+    }
+}
+
+impl ToSql<sql_types::Cidr, Pg> for Cidr {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        to_sql(self, out, 1)
+    }
+}
+
+impl FromSql<sql_types::Inet, Pg> for Cidr {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        from_sql(value, 0)
+    }
+}
+
+impl ToSql<sql_types::Inet, Pg> for Cidr {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        to_sql(self, out, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let cidr: Cidr = "10.1.0.0/16".parse().unwrap();
+        assert_eq!(from_bytes(&[2, 16, 1, 4, 10, 1, 0, 0], 1).unwrap(), cidr);
+        assert!(from_bytes(&[2, 16, 1, 4, 10, 1, 2, 3], 1).is_err());
+        assert_eq!(from_bytes(&[2, 16, 0, 4, 10, 1, 2, 3], 0).unwrap(), cidr);
+        assert!(from_bytes(&[2, 16, 0, 4, 10, 1, 2, 3], 1).is_err());
+        assert!(from_bytes(&[2, 33, 0, 4, 10, 1, 2, 3], 0).is_err());
+        assert!(from_bytes(&[3, 64, 0, 16], 0).is_err());
+    }
+}
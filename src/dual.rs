@@ -0,0 +1,268 @@
+use std::{fmt::Display, net::IpAddr, str::FromStr};
+
+use crate::{Cidr, Cidr6, Error, Fcidr, Fcidr6};
+
+/// A [`Cidr`] or [`Cidr6`], as held by a [`DualFcidr`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum IpCidr {
+    V4(Cidr),
+    V6(Cidr6),
+}
+
+impl From<Cidr> for IpCidr {
+    fn from(value: Cidr) -> Self {
+        Self::V4(value)
+    }
+}
+
+impl From<Cidr6> for IpCidr {
+    fn from(value: Cidr6) -> Self {
+        Self::V6(value)
+    }
+}
+
+impl From<IpAddr> for IpCidr {
+    fn from(value: IpAddr) -> Self {
+        match value {
+            IpAddr::V4(ip) => Self::V4(ip.into()),
+            IpAddr::V6(ip) => Self::V6(ip.into()),
+        }
+    }
+}
+
+impl Display for IpCidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::V4(cidr) => Display::fmt(cidr, f),
+            Self::V6(cidr) => Display::fmt(cidr, f),
+        }
+    }
+}
+
+impl IpCidr {
+    /// Parses like [`FromStr`], but also accepts a bare address as a host
+    /// block.
+    pub fn from_str_lossy(s: &str) -> Result<Self, Error> {
+        if s.contains(':') {
+            Cidr6::from_str_lossy(s).map(Self::V6)
+        } else {
+            Cidr::from_str_lossy(s).map(Self::V4)
+        }
+    }
+
+    /// Parses like [`IpCidr::from_str_lossy`], but clearing host bits instead
+    /// of rejecting them.
+    pub fn from_str_truncate(s: &str) -> Result<Self, Error> {
+        if s.contains(':') {
+            Cidr6::from_str_truncate(s).map(Self::V6)
+        } else {
+            Cidr::from_str_truncate(s).map(Self::V4)
+        }
+    }
+}
+
+/// Parses a [`Cidr6`] if the network contains a `:`, and a [`Cidr`] otherwise.
+impl FromStr for IpCidr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(':') {
+            s.parse().map(Self::V6)
+        } else {
+            s.parse().map(Self::V4)
+        }
+    }
+}
+
+/// An IPv4 [`Fcidr`] and an IPv6 [`Fcidr6`] managed together, each operation
+/// dispatching to the set of the block's address family.
+#[derive(Clone, Debug, Default)]
+pub struct DualFcidr {
+    v4: Fcidr,
+    v6: Fcidr6,
+}
+
+impl DualFcidr {
+    pub fn new<T>(cidr: T) -> Self
+    where
+        T: Into<IpCidr>,
+    {
+        let mut fcidr = Self::default();
+        fcidr.union(cidr);
+        fcidr
+    }
+
+    pub fn v4(&self) -> &Fcidr {
+        &self.v4
+    }
+
+    pub fn v6(&self) -> &Fcidr6 {
+        &self.v6
+    }
+
+    pub fn v4_mut(&mut self) -> &mut Fcidr {
+        &mut self.v4
+    }
+
+    pub fn v6_mut(&mut self) -> &mut Fcidr6 {
+        &mut self.v6
+    }
+
+    /// Complements both address families.
+    pub fn complement(&mut self) -> &mut Self {
+        self.v4.complement();
+        self.v6.complement();
+        self
+    }
+
+    pub fn difference<T>(&mut self, cidr: T) -> &mut Self
+    where
+        T: Into<IpCidr>,
+    {
+        match cidr.into() {
+            IpCidr::V4(cidr) => {
+                self.v4.difference(cidr);
+            }
+            IpCidr::V6(cidr) => {
+                self.v6.difference(cidr);
+            }
+        }
+        self
+    }
+
+    pub fn symmetric_difference<T>(&mut self, cidr: T) -> &mut Self
+    where
+        T: Into<IpCidr>,
+    {
+        match cidr.into() {
+            IpCidr::V4(cidr) => {
+                self.v4.symmetric_difference(cidr);
+            }
+            IpCidr::V6(cidr) => {
+                self.v6.symmetric_difference(cidr);
+            }
+        }
+        self
+    }
+
+    pub fn union<T>(&mut self, cidr: T) -> &mut Self
+    where
+        T: Into<IpCidr>,
+    {
+        match cidr.into() {
+            IpCidr::V4(cidr) => {
+                self.v4.union(cidr);
+            }
+            IpCidr::V6(cidr) => {
+                self.v6.union(cidr);
+            }
+        }
+        self
+    }
+
+    pub fn is_superset<T>(&self, cidr: T) -> bool
+    where
+        T: Into<IpCidr>,
+    {
+        match cidr.into() {
+            IpCidr::V4(cidr) => self.v4.is_superset(cidr),
+            IpCidr::V6(cidr) => self.v6.is_superset(cidr),
+        }
+    }
+
+    /// Yields the IPv4 blocks followed by the IPv6 blocks.
+    pub fn iter(&self) -> impl Iterator<Item = IpCidr> + '_ {
+        self.v4
+            .iter()
+            .map(IpCidr::V4)
+            .chain(self.v6.iter().map(IpCidr::V6))
+    }
+}
+
+impl From<Fcidr> for DualFcidr {
+    fn from(value: Fcidr) -> Self {
+        Self {
+            v4: value,
+            v6: Fcidr6::default(),
+        }
+    }
+}
+
+impl From<Fcidr6> for DualFcidr {
+    fn from(value: Fcidr6) -> Self {
+        Self {
+            v4: Fcidr::default(),
+            v6: value,
+        }
+    }
+}
+
+impl<T> Extend<T> for DualFcidr
+where
+    T: Into<IpCidr>,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for cidr in iter {
+            self.union(cidr);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for DualFcidr
+where
+    T: Into<IpCidr>,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut fcidr = Self::default();
+        fcidr.extend(iter);
+        fcidr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut fcidr = ["10.0.0.0/8", "2001:db8::/32", "11.0.0.0/8"]
+            .iter()
+            .map(|cidr| cidr.parse::<IpCidr>().unwrap())
+            .collect::<DualFcidr>();
+        fcidr
+            .difference("2001:db8:8000::/33".parse::<Cidr6>().unwrap())
+            .difference("10.128.0.0/9".parse::<Cidr>().unwrap());
+        assert_eq!(
+            fcidr
+                .iter()
+                .map(|cidr| cidr.to_string())
+                .collect::<Vec<_>>(),
+            ["10.0.0.0/9", "11.0.0.0/8", "2001:db8::/33"]
+        );
+        fcidr
+            .symmetric_difference("2001:db8::/32".parse::<Cidr6>().unwrap())
+            .symmetric_difference("2001:db8::/32".parse::<Cidr6>().unwrap());
+        assert!(fcidr.is_superset("2001:db8::1".parse::<IpAddr>().unwrap()));
+        assert!(!fcidr.is_superset("10.200.0.1".parse::<IpAddr>().unwrap()));
+        fcidr.complement();
+        assert!(!fcidr.is_superset("::/1".parse::<Cidr6>().unwrap()));
+        assert!(fcidr.is_superset("8000::/1".parse::<Cidr6>().unwrap()));
+        assert!(fcidr.is_superset("10.200.0.1".parse::<IpAddr>().unwrap()));
+        assert!(matches!(
+            "2001:db8::/129".parse::<IpCidr>(),
+            Err(Error::InvalidPrefix(_))
+        ));
+        assert_eq!(
+            IpCidr::from_str_lossy("10.0.0.1").unwrap().to_string(),
+            "10.0.0.1/32"
+        );
+        assert_eq!(
+            IpCidr::from_str_truncate("10.0.0.1/8").unwrap().to_string(),
+            "10.0.0.0/8"
+        );
+        assert_eq!(
+            "10.0.0.0/8".parse::<IpCidr>().unwrap(),
+            IpCidr::V4("10.0.0.0/8".parse().unwrap())
+        );
+    }
+}
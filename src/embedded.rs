@@ -0,0 +1,357 @@
+//! Compile time parsing behind [`fcidr_static!`](crate::fcidr_static) and
+//! [`cidr!`](crate::cidr). Only `const fn`s are usable here, so the text is
+//! walked byte by byte.
+
+use std::net::Ipv4Addr;
+
+use crate::Cidr;
+
+/// Parses the CIDRs out of the text, one per line, ignoring blank lines and
+/// lines starting with `#`. The resulting blocks are aggregated into the same
+/// canonical blocks an [`Fcidr`](crate::Fcidr) would hold, in ascending order,
+/// and the arrays are padded out with copies of the last block.
+///
+/// # Panics
+///
+/// If `N` is not the number of CIDRs in the text, or any of them is malformed,
+/// which fails compilation when evaluated in a constant.
+pub const fn parse<const N: usize>(text: &str) -> ([u32; N], [u8; N]) {
+    let bytes = text.as_bytes();
+    // Each block is keyed by its network, then prefix, so that sorting the
+    // keys puts covers before their subnets.
+    let mut keys = [0u64; N];
+    let mut len = 0;
+    let mut start = 0;
+    while start < bytes.len() {
+        let mut end = start;
+        while end < bytes.len() && bytes[end] != b'\n' {
+            end += 1;
+        }
+        let (first, last) = trim(bytes, start, end);
+        if first < last && bytes[first] != b'#' {
+            if len == N {
+                panic!("fcidr_static! counted fewer cidrs than it parsed");
+            }
+            let (network, prefix) = cidr(bytes, first, last);
+            keys[len] = (network as u64) << 8 | prefix as u64;
+            len += 1;
+        }
+        start = end + 1;
+    }
+    if len != N {
+        panic!("fcidr_static! counted more cidrs than it parsed");
+    }
+
+    // Heapsort, since the lists are large enough that anything quadratic runs
+    // into the compiler's limits on constant evaluation.
+    let mut end = N;
+    let mut i = N / 2;
+    while end > 1 {
+        if i > 0 {
+            i -= 1;
+        } else {
+            end -= 1;
+            let key = keys[end];
+            keys[end] = keys[0];
+            keys[0] = key;
+        }
+        let mut parent = i;
+        loop {
+            let mut child = 2 * parent + 1;
+            if child >= end {
+                break;
+            }
+            if child + 1 < end && keys[child + 1] > keys[child] {
+                child += 1;
+            }
+            if keys[parent] >= keys[child] {
+                break;
+            }
+            let key = keys[parent];
+            keys[parent] = keys[child];
+            keys[child] = key;
+            parent = child;
+        }
+    }
+
+    // Aggregate as `stream::Aggregate` does, using the front of the arrays as
+    // the stack.
+    let mut networks = [0u32; N];
+    let mut prefixes = [0u8; N];
+    let mut len = 0;
+    let mut i = 0;
+    while i < N {
+        let (network, prefix) = ((keys[i] >> 8) as u32, keys[i] as u8);
+        i += 1;
+        if len > 0 && contains(networks[len - 1], prefixes[len - 1], network) {
+            continue;
+        }
+        networks[len] = network;
+        prefixes[len] = prefix;
+        len += 1;
+        while len > 1
+            && prefixes[len - 2] == prefixes[len - 1]
+            && prefixes[len - 1] > 0
+            && networks[len - 2] ^ networks[len - 1] == 1 << (u32::BITS - prefixes[len - 1] as u32)
+            && networks[len - 2] < networks[len - 1]
+        {
+            len -= 1;
+            prefixes[len - 1] -= 1;
+        }
+    }
+    let mut i = len;
+    while i < N {
+        networks[i] = networks[len - 1];
+        prefixes[i] = prefixes[len - 1];
+        i += 1;
+    }
+    (networks, prefixes)
+}
+
+/// Parses a single CIDR, surrounded by optional whitespace.
+///
+/// # Panics
+///
+/// If the CIDR is malformed, which fails compilation when evaluated in a
+/// constant.
+pub const fn parse_cidr(text: &str) -> Cidr {
+    let bytes = text.as_bytes();
+    let (first, last) = trim(bytes, 0, bytes.len());
+    let (network, prefix) = cidr(bytes, first, last);
+    let [a, b, c, d] = network.to_be_bytes();
+    Cidr::new_const(Ipv4Addr::new(a, b, c, d), prefix)
+}
+
+/// Counts the CIDRs [`parse`] will find in the text.
+pub const fn count(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut count = 0;
+    let mut start = 0;
+    while start < bytes.len() {
+        let mut end = start;
+        while end < bytes.len() && bytes[end] != b'\n' {
+            end += 1;
+        }
+        let (first, last) = trim(bytes, start, end);
+        if first < last && bytes[first] != b'#' {
+            count += 1;
+        }
+        start = end + 1;
+    }
+    count
+}
+
+const fn trim(bytes: &[u8], mut first: usize, mut last: usize) -> (usize, usize) {
+    while first < last && bytes[first].is_ascii_whitespace() {
+        first += 1;
+    }
+    while last > first && bytes[last - 1].is_ascii_whitespace() {
+        last -= 1;
+    }
+    (first, last)
+}
+
+const fn contains(network: u32, prefix: u8, ip: u32) -> bool {
+    prefix == 0 || network >> (u32::BITS - prefix as u32) == ip >> (u32::BITS - prefix as u32)
+}
+
+const fn cidr(bytes: &[u8], first: usize, last: usize) -> (u32, u8) {
+    let mut network = 0u32;
+    let mut octets = 0;
+    let mut i = first;
+    loop {
+        let (octet, end) = number(bytes, i, last);
+        if octet > u8::MAX as u32 {
+            panic!("found an octet greater than 255");
+        }
+        network = network << 8 | octet;
+        octets += 1;
+        i = end + 1;
+        if octets == 4 {
+            if end == last || bytes[end] != b'/' {
+                panic!("found a cidr without a network prefix delimiter");
+            }
+            break;
+        }
+        if end == last || bytes[end] != b'.' {
+            panic!("found a network address without four octets");
+        }
+    }
+    let (prefix, end) = number(bytes, i, last);
+    if end != last {
+        panic!("found trailing characters after a cidr");
+    }
+    if prefix > u32::BITS {
+        panic!("found a network prefix greater than 32");
+    }
+    if prefix < u32::BITS && network << prefix != 0 {
+        panic!("found a network address with bits set after its prefix");
+    }
+    (network, prefix as u8)
+}
+
+/// Parses the decimal number starting at `first`, returning it and the index
+/// just past its last digit.
+const fn number(bytes: &[u8], first: usize, last: usize) -> (u32, usize) {
+    let mut value = 0u32;
+    let mut i = first;
+    while i < last && bytes[i].is_ascii_digit() && i - first < 3 {
+        value = value * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    if i == first {
+        panic!("expected a decimal number");
+    }
+    (value, i)
+}
+
+/// Builds a [`StaticFcidr`](crate::StaticFcidr) from a list of CIDRs, one per
+/// line, parsed entirely at compile time, so that large well-known sets can be
+/// embedded in a binary without parsing them at startup. Blank lines and lines
+/// starting with `#` are ignored, and a malformed CIDR fails compilation.
+///
+/// ```
+/// use fcidr::{fcidr_static, StaticFcidr};
+///
+/// static PRIVATE: StaticFcidr = fcidr_static!(
+///     "# RFC 1918
+///     10.0.0.0/8
+///     172.16.0.0/12
+///     192.168.0.0/16"
+/// );
+///
+/// assert!(PRIVATE.contains("172.20.1.1".parse::<std::net::Ipv4Addr>().unwrap()));
+/// ```
+///
+/// The list is usually bundled with `include_str!`, as in
+/// `fcidr_static!(include_str!("bogons.txt"))`.
+#[macro_export]
+macro_rules! fcidr_static {
+    ($text:expr) => {{
+        const TEXT: &str = $text;
+        const PARSED: (
+            [u32; $crate::__private::count(TEXT)],
+            [u8; $crate::__private::count(TEXT)],
+        ) = $crate::__private::parse(TEXT);
+        $crate::StaticFcidr::from_parts(&PARSED.0, &PARSED.1)
+    }};
+}
+
+/// Builds a [`Cidr`](crate::Cidr) parsed at compile time, so a malformed
+/// literal fails compilation instead of panicking at runtime.
+///
+/// ```
+/// use fcidr::{cidr, Cidr};
+///
+/// const PRIVATE: Cidr = cidr!("10.0.0.0/8");
+///
+/// assert_eq!(PRIVATE, "10.0.0.0/8".parse().unwrap());
+/// ```
+///
+/// ```compile_fail
+/// let _ = fcidr::cidr!("10.0.0.1/8");
+/// ```
+#[macro_export]
+macro_rules! cidr {
+    ($text:expr) => {{
+        const CIDR: $crate::Cidr = $crate::__private::parse_cidr($text);
+        CIDR
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cidr, Fcidr, FrozenFcidr, StaticFcidr};
+
+    static SET: StaticFcidr = fcidr_static!(
+        "
+        # comment
+        192.168.0.0/16
+          10.0.0.0/8
+        10.1.0.0/16
+        0.0.0.0/32
+
+        10.255.255.255/32
+        255.255.255.255/32
+        "
+    );
+
+    #[test]
+    fn it_works() {
+        assert_eq!(SET.len(), 4);
+        for ip in [
+            "0.0.0.0",
+            "10.0.0.0",
+            "10.2.0.0",
+            "10.255.255.255",
+            "192.168.1.1",
+            "255.255.255.255",
+        ] {
+            assert!(
+                SET.contains(ip.parse::<std::net::Ipv4Addr>().unwrap()),
+                "{ip}"
+            );
+        }
+        for ip in ["0.0.0.1", "11.0.0.0", "192.169.0.0", "255.255.255.254"] {
+            assert!(
+                !SET.contains(ip.parse::<std::net::Ipv4Addr>().unwrap()),
+                "{ip}"
+            );
+        }
+        assert!(SET.contains("10.128.0.0/9".parse::<Cidr>().unwrap()));
+        assert_eq!(
+            Fcidr::from(&SET).iter().collect::<Vec<_>>(),
+            [
+                "0.0.0.0/32",
+                "10.0.0.0/8",
+                "192.168.0.0/16",
+                "255.255.255.255/32"
+            ]
+            .iter()
+            .map(|cidr| cidr.parse::<Cidr>().unwrap())
+            .collect::<Vec<_>>()
+        );
+        const EMPTY: StaticFcidr = fcidr_static!("");
+        assert!(EMPTY.is_empty());
+        assert_eq!(cidr!(" 192.168.0.0/16 "), "192.168.0.0/16".parse().unwrap());
+        assert_eq!(cidr!("0.0.0.0/0"), Cidr::default());
+        assert_eq!(
+            cidr!("255.255.255.255/32"),
+            Cidr::from(std::net::Ipv4Addr::BROADCAST)
+        );
+    }
+
+    #[test]
+    fn canonical() {
+        static ADJACENT: StaticFcidr = fcidr_static!(
+            "10.128.0.0/9
+            10.0.0.0/10
+            10.64.0.0/10
+            12.0.0.0/8"
+        );
+        assert_eq!(ADJACENT.len(), 2);
+        assert!(ADJACENT.contains("10.0.0.0/8".parse::<Cidr>().unwrap()));
+        assert!(!ADJACENT.contains("10.0.0.0/7".parse::<Cidr>().unwrap()));
+        assert!(FrozenFcidr::from(&ADJACENT).contains("10.0.0.0/8".parse::<Cidr>().unwrap()));
+        assert_eq!(
+            ADJACENT.iter().collect::<Vec<_>>(),
+            ["10.0.0.0/8".parse().unwrap(), "12.0.0.0/8".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn large() {
+        const LIST: &str = include_str!("../testdata/static.txt");
+        static LARGE: StaticFcidr = fcidr_static!(LIST);
+        let fcidr = LIST
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| line.parse::<Cidr>().unwrap())
+            .collect::<Fcidr>();
+        assert_eq!(LARGE.iter().collect::<Vec<_>>(), fcidr.to_vec());
+        assert_eq!(FrozenFcidr::from(&LARGE), FrozenFcidr::from(&fcidr));
+        for cidr in &fcidr {
+            assert!(LARGE.contains(cidr), "{cidr}");
+        }
+    }
+}
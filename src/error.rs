@@ -1,16 +1,94 @@
 use std::{error, fmt};
 
+use crate::{Cidr, Fcidr};
+
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub enum Error {
+    Conflict(String),
+    Exhausted(String),
+    InvalidNetmask(String),
     InvalidNetwork(String),
     InvalidPrefix(String),
+    LimitExceeded(String),
+    NotFound(String),
     Parse(String),
+    /// An error encountered at a specific position of a multi-value input.
+    /// Lines and columns are both counted from 1.
+    Syntax {
+        line: usize,
+        column: usize,
+        token: String,
+        error: Box<Error>,
+    },
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        match self {
+            Self::Syntax {
+                line,
+                column,
+                token,
+                error,
+            } => write!(f, "line {line}, column {column}: {error} in '{token}'"),
+            Self::Conflict(message)
+            | Self::Exhausted(message)
+            | Self::InvalidNetmask(message)
+            | Self::InvalidNetwork(message)
+            | Self::InvalidPrefix(message)
+            | Self::LimitExceeded(message)
+            | Self::NotFound(message)
+            | Self::Parse(message) => f.write_str(message),
+        }
     }
 }
 
 impl error::Error for Error {}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NotSupersetError {
+    cidr: Cidr,
+    remainder: Fcidr,
+}
+
+impl NotSupersetError {
+    pub(crate) fn new(cidr: Cidr, remainder: Fcidr) -> Self {
+        Self { cidr, remainder }
+    }
+
+    pub fn cidr(&self) -> Cidr {
+        self.cidr
+    }
+
+    /// The part of the CIDR not covered by the set.
+    pub fn remainder(&self) -> &Fcidr {
+        &self.remainder
+    }
+}
+
+impl fmt::Display for NotSupersetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not a superset of {}", self.cidr)
+    }
+}
+
+impl error::Error for NotSupersetError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let error = Error::Syntax {
+            line: 3,
+            column: 5,
+            token: "10.0.0.0/33".to_string(),
+            error: Box::new(Error::InvalidPrefix("too long".to_string())),
+        };
+        assert_eq!(
+            error.to_string(),
+            "line 3, column 5: too long in '10.0.0.0/33'"
+        );
+    }
+}
@@ -0,0 +1,21 @@
+//! Exit codes used by the `fcidr` binary.
+
+/// The computation succeeded, or a predicate subcommand held.
+pub const SUCCESS: i32 = 0;
+/// A predicate subcommand, such as `superset`, did not hold.
+pub const FAILURE: i32 = 1;
+/// The command line could not be understood, or no input was given.
+pub const USAGE: i32 = 2;
+/// For `compare`, the input CIDRs are a strict subset of the operand.
+pub const SUBSET: i32 = 3;
+/// For `compare`, the input CIDRs are a strict superset of the operand.
+pub const SUPERSET: i32 = 4;
+/// For `compare`, the input CIDRs and the operand share some but not all
+/// addresses.
+pub const OVERLAPPING: i32 = 5;
+/// For `compare`, the input CIDRs and the operand share no addresses.
+pub const DISJOINT: i32 = 6;
+/// The input CIDRs or pipeline description were malformed.
+pub const DATA: i32 = 65;
+/// An input could not be read or an output could not be written.
+pub const IO: i32 = 74;
@@ -1,13 +1,28 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    net::Ipv4Addr,
+    str::FromStr,
+    sync::Arc,
+};
 
-use crate::Cidr;
+use crate::{Cidr, Error, NotSupersetError, Transaction};
 
-#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
-enum Inclusion {
+/// How much of a block a set covers, as told by [`Fcidr::inclusion_of`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Inclusion {
+    Included,
+    Excluded,
+    Partial,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum NodeInclusion {
     #[default]
     Excluded,
     Included,
-    Subnets([Rc<RefCell<CidrNode>>; 2]),
+    Subnets([NodeId; 2]),
 }
 
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -16,162 +31,789 @@ enum BinarySetOperator {
     Union,
 }
 
-impl Into<Inclusion> for BinarySetOperator {
-    fn into(self) -> Inclusion {
-        match self {
-            BinarySetOperator::Difference => Inclusion::Excluded,
-            BinarySetOperator::Union => Inclusion::Included,
+impl From<BinarySetOperator> for NodeInclusion {
+    fn from(value: BinarySetOperator) -> Self {
+        match value {
+            BinarySetOperator::Difference => NodeInclusion::Excluded,
+            BinarySetOperator::Union => NodeInclusion::Included,
         }
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+/// The position of a node in its [`Tree`].
+type NodeId = u32;
+
+const ROOT: NodeId = 0;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 struct CidrNode {
     cidr: Cidr,
-    inclusion: Inclusion,
+    inclusion: NodeInclusion,
+    /// The number of included blocks in the subtree.
+    blocks: usize,
+    /// The number of addresses those blocks cover.
+    addresses: u64,
 }
 
 impl CidrNode {
-    fn new(cidr: Cidr) -> Self {
+    fn leaf(cidr: Cidr, inclusion: NodeInclusion) -> Self {
+        let included = inclusion == NodeInclusion::Included;
         Self {
             cidr,
-            inclusion: Default::default(),
+            inclusion,
+            blocks: included.into(),
+            addresses: if included { cidr.size() } else { 0 },
+        }
+    }
+}
+
+/// The nodes of the trie in one allocation, linked by their positions, with
+/// the root at [`ROOT`]. Slots of merged away nodes are kept for reuse rather
+/// than shifting the rest.
+#[derive(Clone, Debug)]
+struct Tree {
+    nodes: Vec<CidrNode>,
+    free: Vec<NodeId>,
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        Self {
+            nodes: vec![CidrNode::default()],
+            free: Vec::new(),
+        }
+    }
+}
+
+impl Tree {
+    fn node(&self, id: NodeId) -> &CidrNode {
+        &self.nodes[id as usize]
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut CidrNode {
+        &mut self.nodes[id as usize]
+    }
+
+    fn root(&self) -> &CidrNode {
+        self.node(ROOT)
+    }
+
+    fn allocate(&mut self, node: CidrNode) -> NodeId {
+        match self.free.pop() {
+            Some(id) => {
+                *self.node_mut(id) = node;
+                id
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as NodeId
+            }
         }
     }
 
-    fn binary_set_operation(&mut self, cidr: Cidr, operator: BinarySetOperator) -> &mut Self {
-        if self.cidr == cidr {
-            self.inclusion = operator.into();
-        } else if self.cidr.contains(cidr) && self.inclusion != operator.into() {
-            let subnets = match &self.inclusion {
-                Inclusion::Subnets([left, right]) => [left.clone(), right.clone()],
+    /// Frees the node and its whole subtree.
+    fn release(&mut self, id: NodeId) {
+        if let NodeInclusion::Subnets([left, right]) = self.node(id).inclusion {
+            self.release(left);
+            self.release(right);
+        }
+        self.free.push(id);
+    }
+
+    /// Sets the inclusion of the node, freeing any subnets it replaces.
+    fn set_inclusion(&mut self, id: NodeId, inclusion: NodeInclusion) {
+        if let NodeInclusion::Subnets([left, right]) = self.node(id).inclusion {
+            self.release(left);
+            self.release(right);
+        }
+        self.node_mut(id).inclusion = inclusion;
+    }
+
+    fn count_blocks(&mut self, id: NodeId) {
+        let node = self.node(id);
+        let (blocks, addresses) = match node.inclusion {
+            NodeInclusion::Excluded => (0, 0),
+            NodeInclusion::Included => (1, node.cidr.size()),
+            NodeInclusion::Subnets([left, right]) => {
+                let (left, right) = (self.node(left), self.node(right));
+                (left.blocks + right.blocks, left.addresses + right.addresses)
+            }
+        };
+        let node = self.node_mut(id);
+        node.blocks = blocks;
+        node.addresses = addresses;
+    }
+
+    /// Returns the number of nodes allocated by the operation.
+    fn binary_set_operation(
+        &mut self,
+        id: NodeId,
+        cidr: Cidr,
+        operator: BinarySetOperator,
+    ) -> usize {
+        let mut allocated = 0;
+        let node = *self.node(id);
+        if node.cidr == cidr {
+            self.set_inclusion(id, operator.into());
+        } else if node.cidr.contains(cidr) && node.inclusion != operator.into() {
+            let [left, right] = match node.inclusion {
+                NodeInclusion::Subnets(subnets) => subnets,
                 inclusion => {
-                    let [left, right] = [
-                        Rc::new(RefCell::new(CidrNode {
-                            cidr: self.cidr.left_subnet().unwrap(),
-                            inclusion: inclusion.to_owned(),
-                        })),
-                        Rc::new(RefCell::new(CidrNode {
-                            cidr: self.cidr.right_subnet().unwrap(),
-                            inclusion: inclusion.to_owned(),
-                        })),
+                    let subnets = [
+                        self.allocate(CidrNode::leaf(node.cidr.left_subnet().unwrap(), inclusion)),
+                        self.allocate(CidrNode::leaf(node.cidr.right_subnet().unwrap(), inclusion)),
                     ];
-                    self.inclusion = Inclusion::Subnets([left.clone(), right.clone()]);
-                    [left, right]
+                    self.node_mut(id).inclusion = NodeInclusion::Subnets(subnets);
+                    allocated += 2;
+                    subnets
                 }
             };
-            for subnet in &subnets {
-                subnet.borrow_mut().binary_set_operation(cidr, operator);
+            let subnet = if cidr.network() < node.cidr.mid() {
+                left
+            } else {
+                right
+            };
+            allocated += self.binary_set_operation(subnet, cidr, operator);
+            if [left, right]
+                .iter()
+                .all(|&subnet| self.node(subnet).inclusion == operator.into())
+            {
+                self.set_inclusion(id, operator.into());
+            }
+        }
+        self.count_blocks(id);
+        allocated
+    }
+
+    fn complement(&mut self, id: NodeId) {
+        match self.node(id).inclusion {
+            NodeInclusion::Excluded => self.node_mut(id).inclusion = NodeInclusion::Included,
+            NodeInclusion::Included => self.node_mut(id).inclusion = NodeInclusion::Excluded,
+            NodeInclusion::Subnets([left, right]) => {
+                self.complement(left);
+                self.complement(right);
             }
-            if subnets
+        }
+        self.count_blocks(id);
+    }
+
+    fn restrict(&mut self, id: NodeId, universe: Cidr) {
+        let node = *self.node(id);
+        if universe.contains(node.cidr) {
+            return;
+        }
+        if !node.cidr.contains(universe) {
+            self.set_inclusion(id, NodeInclusion::Excluded);
+        } else if let NodeInclusion::Subnets([left, right]) = node.inclusion {
+            self.restrict(left, universe);
+            self.restrict(right, universe);
+            if [left, right]
                 .iter()
-                .all(|subnet| subnet.borrow().inclusion == operator.into())
+                .all(|&subnet| self.node(subnet).inclusion == NodeInclusion::Excluded)
             {
-                self.inclusion = operator.into();
+                self.set_inclusion(id, NodeInclusion::Excluded);
+            }
+        } else if node.inclusion == NodeInclusion::Included {
+            self.node_mut(id).inclusion = NodeInclusion::Excluded;
+            self.binary_set_operation(id, universe, BinarySetOperator::Union);
+        }
+        self.count_blocks(id);
+    }
+
+    fn get(&self, id: NodeId, index: usize) -> Option<Cidr> {
+        let node = self.node(id);
+        match node.inclusion {
+            NodeInclusion::Excluded => None,
+            NodeInclusion::Included => (index == 0).then_some(node.cidr),
+            NodeInclusion::Subnets([left, right]) => {
+                let blocks = self.node(left).blocks;
+                if index < blocks {
+                    self.get(left, index)
+                } else {
+                    self.get(right, index - blocks)
+                }
             }
         }
-        self
     }
 
-    fn contains(&self, cidr: Cidr) -> bool {
-        if cidr.prefix() < self.cidr.prefix() {
+    fn inclusion_of(&self, id: NodeId, cidr: Cidr) -> Inclusion {
+        let node = self.node(id);
+        match node.inclusion {
+            NodeInclusion::Excluded => Inclusion::Excluded,
+            NodeInclusion::Included => Inclusion::Included,
+            NodeInclusion::Subnets([left, right]) if cidr.contains(node.cidr) => {
+                match self.inclusion_of(left, cidr) {
+                    Inclusion::Partial => Inclusion::Partial,
+                    inclusion if self.inclusion_of(right, cidr) == inclusion => inclusion,
+                    _ => Inclusion::Partial,
+                }
+            }
+            NodeInclusion::Subnets([left, right]) => {
+                if cidr.network() < node.cidr.mid() {
+                    self.inclusion_of(left, cidr)
+                } else {
+                    self.inclusion_of(right, cidr)
+                }
+            }
+        }
+    }
+
+    fn contains(&self, id: NodeId, cidr: Cidr) -> bool {
+        let node = self.node(id);
+        if cidr.prefix() < node.cidr.prefix() {
             return false;
         }
-        match &self.inclusion {
-            Inclusion::Excluded => false,
-            Inclusion::Included => self.cidr.contains(cidr),
-            Inclusion::Subnets([left, right]) => {
-                if cidr.network() < self.cidr.mid() {
-                    left.borrow().contains(cidr)
+        match node.inclusion {
+            NodeInclusion::Excluded => false,
+            NodeInclusion::Included => node.cidr.contains(cidr),
+            NodeInclusion::Subnets([left, right]) => {
+                if cidr.network() < node.cidr.mid() {
+                    self.contains(left, cidr)
                 } else {
-                    right.borrow().contains(cidr)
+                    self.contains(right, cidr)
                 }
             }
         }
     }
+
+    /// Pops nodes off the stack, pushing the subnets of any split, until an
+    /// included block turns up.
+    fn walk(&self, next: &mut Vec<NodeId>) -> Option<Cidr> {
+        while let Some(id) = next.pop() {
+            let node = self.node(id);
+            match node.inclusion {
+                NodeInclusion::Excluded => continue,
+                NodeInclusion::Included => return Some(node.cidr),
+                NodeInclusion::Subnets([left, right]) => {
+                    next.push(right);
+                    next.push(left);
+                }
+            }
+        }
+        None
+    }
 }
 
-#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+/// Cloning is cheap, as the tree is shared between clones and only copied
+/// once either of them changes, so changes to a clone never show through in
+/// the original.
+///
+/// Sets compare, order and hash by the blocks they cover, regardless of how
+/// they were built.
+#[derive(Clone, Debug, Default)]
 pub struct Fcidr {
-    cidr: Rc<RefCell<CidrNode>>,
+    tree: Arc<Tree>,
 }
 
 impl Fcidr {
     pub fn new(cidr: Cidr) -> Self {
         let mut fcidr = Self::default();
-        let mut next = vec![Rc::new(RefCell::new(CidrNode {
-            cidr,
-            inclusion: Inclusion::Included,
-        }))];
-        while let Some(n) = next.pop() {
-            if let (Some(parent), cidr) = (n.borrow().cidr.parent(), n.borrow().cidr) {
-                next.push(Rc::new(RefCell::new(CidrNode {
-                    cidr: parent,
-                    inclusion: Inclusion::Subnets(
-                        if (u32::from(cidr.network()) >> (u32::BITS - cidr.prefix() as u32)) & 1
-                            == 0
-                        {
-                            [
-                                n.clone(),
-                                Rc::new(RefCell::new(CidrNode::new(
-                                    parent.right_subnet().unwrap(),
-                                ))),
-                            ]
-                        } else {
-                            [
-                                Rc::new(RefCell::new(CidrNode::new(parent.left_subnet().unwrap()))),
-                                n.clone(),
-                            ]
-                        },
-                    ),
-                })));
-            } else {
-                fcidr.cidr = n.clone();
-            }
-        }
+        fcidr
+            .tree_mut()
+            .binary_set_operation(ROOT, cidr, BinarySetOperator::Union);
         fcidr
     }
 
-    pub fn complement(&mut self) -> &mut Self {
-        let mut next = vec![self.cidr.clone()];
-        while let Some(node) = next.pop() {
-            let mut node = node.borrow_mut();
-            match &node.inclusion {
-                Inclusion::Excluded => node.inclusion = Inclusion::Included,
-                Inclusion::Included => node.inclusion = Inclusion::Excluded,
-                Inclusion::Subnets(subnet) => {
-                    for s in subnet {
-                        next.push(s.clone());
+    fn tree_mut(&mut self) -> &mut Tree {
+        Arc::make_mut(&mut self.tree)
+    }
+
+    /// Builds the union of the inclusive `(first, last)` address ranges, which
+    /// [`RangeInclusive::into_inner`](std::ops::RangeInclusive::into_inner)
+    /// yields for `first..=last`.
+    pub fn from_ranges<T>(ranges: T) -> Self
+    where
+        T: IntoIterator<Item = (Ipv4Addr, Ipv4Addr)>,
+    {
+        ranges
+            .into_iter()
+            .flat_map(|(first, last)| Cidr::cover_range(first, last))
+            .collect()
+    }
+
+    pub fn begin(&mut self) -> Transaction<'_> {
+        Transaction::new(self)
+    }
+
+    pub(crate) fn covered_within(&self, cidr: Cidr) -> Vec<Cidr> {
+        let mut id = ROOT;
+        loop {
+            let node = self.tree.node(id);
+            match node.inclusion {
+                NodeInclusion::Excluded => return Vec::new(),
+                NodeInclusion::Included => return vec![cidr],
+                NodeInclusion::Subnets([left, right]) => {
+                    if node.cidr == cidr {
+                        return FcidrIter {
+                            tree: &self.tree,
+                            next: vec![id],
+                        }
+                        .collect();
+                    } else if cidr.network() < node.cidr.mid() {
+                        id = left;
+                    } else {
+                        id = right;
                     }
                 }
             }
         }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn complement(&mut self) -> &mut Self {
+        self.tree_mut().complement(ROOT);
         self
     }
 
+    pub fn complemented(&self) -> Self {
+        let mut fcidr = self.clone();
+        fcidr.complement();
+        fcidr
+    }
+
     pub fn difference(&mut self, cidr: Cidr) -> &mut Self {
-        self.cidr
-            .borrow_mut()
-            .binary_set_operation(cidr, BinarySetOperator::Difference);
+        self.tree_mut()
+            .binary_set_operation(ROOT, cidr, BinarySetOperator::Difference);
+        self
+    }
+
+    /// Toggles the coverage of every address in the block, so what was covered
+    /// within it no longer is and vice versa.
+    pub fn symmetric_difference(&mut self, cidr: Cidr) -> &mut Self {
+        let covered = self.covered_within(cidr);
+        self.union(cidr);
+        for block in covered {
+            self.difference(block);
+        }
+        self
+    }
+
+    pub fn symmetric_differenced(&self, cidr: Cidr) -> Self {
+        let mut fcidr = self.clone();
+        fcidr.symmetric_difference(cidr);
+        fcidr
+    }
+
+    /// Drops all coverage outside of the universe.
+    pub fn restrict(&mut self, universe: Cidr) -> &mut Self {
+        self.tree_mut().restrict(ROOT, universe);
+        self
+    }
+
+    /// Splits the coverage into the addresses below `ip` and those at or above
+    /// it.
+    pub fn split_at(&self, ip: Ipv4Addr) -> (Fcidr, Fcidr) {
+        let (mut below, mut above) = (Fcidr::default(), Fcidr::default());
+        for cidr in self {
+            if cidr.last() < ip {
+                below.union(cidr);
+            } else if cidr.first() >= ip {
+                above.union(cidr);
+            } else {
+                below.extend(Cidr::cover_range(cidr.first(), (u32::from(ip) - 1).into()));
+                above.extend(Cidr::cover_range(ip, cidr.last()));
+            }
+        }
+        (below, above)
+    }
+
+    /// Splits the blocks into those matching the predicate and those that do
+    /// not.
+    pub fn partition<F>(&self, pred: F) -> (Fcidr, Fcidr)
+    where
+        F: Fn(Cidr) -> bool,
+    {
+        let (mut matching, mut rest) = (Fcidr::default(), Fcidr::default());
+        for cidr in self {
+            if pred(cidr) {
+                matching.union(cidr);
+            } else {
+                rest.union(cidr);
+            }
+        }
+        (matching, rest)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = Ipv4Addr> + '_ {
+        self.iter()
+            .flat_map(|cidr| (u32::from(cidr.first())..=u32::from(cidr.last())).map(Ipv4Addr::from))
+    }
+
+    /// Like [`Fcidr::addresses`], but fails up front rather than expanding more
+    /// than `limit` addresses.
+    pub fn addresses_capped(
+        &self,
+        limit: u64,
+    ) -> Result<impl Iterator<Item = Ipv4Addr> + '_, Error> {
+        let count = self.num_addresses();
+        if count > limit {
+            return Err(Error::LimitExceeded(format!(
+                "set covers {count} addresses, more than the limit of {limit}"
+            )));
+        }
+        Ok(self.addresses())
+    }
+
+    pub fn differenced(&self, cidr: Cidr) -> Self {
+        let mut fcidr = self.clone();
+        fcidr.difference(cidr);
+        fcidr
+    }
+
+    pub fn difference_set(&mut self, other: &Fcidr) -> &mut Self {
+        for cidr in other {
+            self.difference(cidr);
+        }
         self
     }
 
+    /// Returns the fraction of the IPv4 address space covered by the set.
+    pub fn coverage(&self) -> f64 {
+        self.covered_fraction(None)
+    }
+
+    /// Returns the fraction of the universe, or of the whole IPv4 address
+    /// space if none is given, covered by the set.
+    pub fn covered_fraction(&self, universe: Option<Cidr>) -> f64 {
+        let universe = universe.unwrap_or_default();
+        let covered = self
+            .covered_within(universe)
+            .iter()
+            .map(|cidr| cidr.size())
+            .sum::<u64>();
+        covered as f64 / universe.size() as f64
+    }
+
+    /// Returns the number of covered addresses within each block of the given
+    /// prefix length, omitting blocks with no coverage.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub fn histogram(&self, prefix: u8) -> Result<Vec<(Cidr, u64)>, Error> {
+        if prefix as u32 > u32::BITS {
+            return Err(Error::InvalidPrefix(format!(
+                "network prefix '{prefix}' must be 32 or less"
+            )));
+        }
+        let mut histogram: Vec<(Cidr, u64)> = Vec::new();
+        for cidr in self {
+            if cidr.prefix() >= prefix {
+                let bucket = Cidr::truncate(cidr.network(), prefix);
+                match histogram.last_mut() {
+                    Some((last, count)) if *last == bucket => *count += cidr.size(),
+                    _ => histogram.push((bucket, cidr.size())),
+                }
+            } else {
+                let size = 1 << (u32::BITS - prefix as u32);
+                let first = u32::from(cidr.first()) as u64;
+                histogram.extend((0..cidr.size() / size).map(|i| {
+                    let network = Ipv4Addr::from((first + i * size) as u32);
+                    (Cidr::truncate(network, prefix), size)
+                }));
+            }
+        }
+        Ok(histogram)
+    }
+
+    /// Returns the smallest single CIDR containing the whole set, if any.
+    pub fn supernet(&self) -> Option<Cidr> {
+        let first = u32::from(self.get(0)?.first());
+        let last = u32::from(self.get(self.tree.root().blocks - 1)?.last());
+        Some(Cidr::truncate(
+            first.into(),
+            (first ^ last).leading_zeros() as u8,
+        ))
+    }
+
+    /// Returns one bit per address of the universe, set if the address is
+    /// covered. Address `n` of the universe is bit `n % 8` (least significant
+    /// first) of byte `n / 8`.
+    pub fn to_bitmap(&self, universe: Cidr) -> Vec<u8> {
+        let mut bitmap = vec![0; ((universe.size() + 7) / 8) as usize];
+        let base = u32::from(universe.first()) as u64;
+        for cidr in self.covered_within(universe) {
+            let first = u32::from(cidr.first()) as u64 - base;
+            let last = u32::from(cidr.last()) as u64 - base;
+            if cidr.size() >= 8 {
+                bitmap[(first / 8) as usize..=(last / 8) as usize].fill(u8::MAX);
+            } else {
+                for n in first..=last {
+                    bitmap[(n / 8) as usize] |= 1 << (n % 8);
+                }
+            }
+        }
+        bitmap
+    }
+
+    /// The inverse of [`Fcidr::to_bitmap`]. Bits beyond the universe are
+    /// ignored and missing bits are treated as unset.
+    pub fn from_bitmap(universe: Cidr, bits: &[u8]) -> Self {
+        let base = u32::from(universe.first()) as u64;
+        let len = universe.size().min(bits.len() as u64 * 8);
+        let mut fcidr = Self::default();
+        let mut run: Option<u64> = None;
+        let mut n = 0;
+        while n <= len {
+            let byte = bits.get((n / 8) as usize).copied().unwrap_or_default();
+            let set = n < len && byte & (1 << (n % 8)) != 0;
+            match (run, set) {
+                (None, true) => run = Some(n),
+                (Some(first), false) => {
+                    fcidr.extend(Cidr::cover_range(
+                        ((base + first) as u32).into(),
+                        ((base + n - 1) as u32).into(),
+                    ));
+                    run = None;
+                }
+                _ => {}
+            }
+            // Skip over whole bytes that do not end or start a run.
+            n += match (run, byte) {
+                (Some(_), u8::MAX) | (None, 0) if n % 8 == 0 && n + 8 <= len => 8,
+                _ => 1,
+            };
+        }
+        fcidr
+    }
+
+    /// Returns the number of blocks of each prefix length, indexed by length.
+    pub fn prefix_histogram(&self) -> [u64; 33] {
+        let mut histogram = [0; 33];
+        for cidr in self {
+            histogram[cidr.prefix() as usize] += 1;
+        }
+        histogram
+    }
+
     pub fn is_superset(&self, cidr: Cidr) -> bool {
-        self.cidr.borrow().contains(cidr)
+        self.tree.contains(ROOT, cidr)
+    }
+
+    /// Tells whether the block is wholly covered, wholly uncovered or only
+    /// partially covered, where [`Fcidr::is_superset`] only tells the first.
+    pub fn inclusion_of(&self, cidr: Cidr) -> Inclusion {
+        self.tree.inclusion_of(ROOT, cidr)
+    }
+
+    /// Like [`Fcidr::is_superset`], but also taking addresses, as
+    /// [`Cidr::contains`] does.
+    pub fn contains<T>(&self, net: T) -> bool
+    where
+        T: Into<Cidr>,
+    {
+        self.is_superset(net.into())
+    }
+
+    pub fn contains_ip(&self, ip: Ipv4Addr) -> bool {
+        self.contains(ip)
+    }
+
+    pub fn require_superset(&self, cidr: Cidr) -> Result<(), NotSupersetError> {
+        if self.is_superset(cidr) {
+            return Ok(());
+        }
+        let mut remainder = Self::new(cidr);
+        for block in self.covered_within(cidr) {
+            remainder.difference(block);
+        }
+        Err(NotSupersetError::new(cidr, remainder))
+    }
+
+    /// Returns the covered address numerically closest to `ip`, preferring the
+    /// lower one on ties.
+    pub fn nearest(&self, ip: Ipv4Addr) -> Option<Ipv4Addr> {
+        if self.is_superset(ip.into()) {
+            return Some(ip);
+        }
+        // Binary search for the first block after the address.
+        let (mut low, mut high) = (0, self.tree.root().blocks);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.get(mid)?.first() < ip {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let below = low
+            .checked_sub(1)
+            .and_then(|i| self.get(i))
+            .map(|c| c.last());
+        let above = self.get(low).map(|c| c.first());
+        match (below, above) {
+            (Some(below), Some(above)) => {
+                if u32::from(ip) - u32::from(below) <= u32::from(above) - u32::from(ip) {
+                    Some(below)
+                } else {
+                    Some(above)
+                }
+            }
+            (below, above) => below.or(above),
+        }
     }
 
     pub fn union(&mut self, cidr: Cidr) -> &mut Self {
-        self.cidr
-            .borrow_mut()
-            .binary_set_operation(cidr, BinarySetOperator::Union);
+        self.insert(cidr);
         self
     }
 
-    pub fn iter(&self) -> FcidrIntoIterator {
-        FcidrIntoIterator {
-            next: vec![self.cidr.clone()],
+    pub fn unioned(&self, cidr: Cidr) -> Self {
+        let mut fcidr = self.clone();
+        fcidr.union(cidr);
+        fcidr
+    }
+
+    /// Unions the inclusive address range, which need not be aligned to a
+    /// prefix.
+    pub fn union_range(&mut self, first: Ipv4Addr, last: Ipv4Addr) -> &mut Self {
+        for cidr in Cidr::cover_range(first, last) {
+            self.union(cidr);
+        }
+        self
+    }
+
+    pub fn union_set(&mut self, other: &Fcidr) -> &mut Self {
+        for cidr in other {
+            self.union(cidr);
+        }
+        self
+    }
+
+    /// Returns the number of nodes allocated by the union.
+    fn insert(&mut self, cidr: Cidr) -> usize {
+        self.tree_mut()
+            .binary_set_operation(ROOT, cidr, BinarySetOperator::Union)
+    }
+
+    pub fn iter(&self) -> FcidrIter<'_> {
+        FcidrIter {
+            tree: &self.tree,
+            next: vec![ROOT],
+        }
+    }
+
+    /// Iterates over the blocks at or above `ip`, starting with the block
+    /// containing it, if any. Only the path to `ip` is descended to find the
+    /// starting point, so a scan can be resumed cheaply.
+    pub fn iter_from(&self, ip: Ipv4Addr) -> FcidrIter<'_> {
+        let mut next = Vec::new();
+        let mut id = ROOT;
+        loop {
+            let node = self.tree.node(id);
+            match node.inclusion {
+                NodeInclusion::Excluded => break,
+                NodeInclusion::Included => {
+                    next.push(id);
+                    break;
+                }
+                NodeInclusion::Subnets([left, right]) => {
+                    if ip < node.cidr.mid() {
+                        next.push(right);
+                        id = left;
+                    } else {
+                        id = right;
+                    }
+                }
+            }
+        }
+        FcidrIter {
+            tree: &self.tree,
+            next,
         }
     }
+
+    /// Returns the number of addresses covered, kept up to date by every
+    /// operation rather than summed over the blocks.
+    pub fn num_addresses(&self) -> u64 {
+        self.tree.root().addresses
+    }
+
+    /// Returns the number of blocks [`Fcidr::iter`] yields.
+    pub fn num_prefixes(&self) -> usize {
+        self.tree.root().blocks
+    }
+
+    /// Returns the block at the given position in iteration order, descending
+    /// only the path to it.
+    pub fn get(&self, index: usize) -> Option<Cidr> {
+        self.tree.get(ROOT, index)
+    }
+
+    pub fn to_vec(&self) -> Vec<Cidr> {
+        let mut cidrs = Vec::with_capacity(self.tree.root().blocks);
+        cidrs.extend(self);
+        cidrs
+    }
+}
+
+impl PartialEq for Fcidr {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Fcidr {}
+
+impl PartialOrd for Fcidr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fcidr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl Hash for Fcidr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tree.root().blocks.hash(state);
+        for cidr in self {
+            cidr.hash(state);
+        }
+    }
+}
+
+/// Writes one block per line, or with `{:#}` all on one line separated by
+/// commas.
+impl Display for Fcidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let separator = if f.alternate() { ", " } else { "\n" };
+        for (i, cidr) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(separator)?;
+            }
+            write!(f, "{cidr}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the union of CIDRs separated by whitespace and/or commas, reporting
+/// a malformed one with [`Error::Syntax`].
+impl FromStr for Fcidr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fcidr = Self::default();
+        for (index, line) in s.lines().enumerate() {
+            for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+                if token.is_empty() {
+                    continue;
+                }
+                let cidr = token.parse().map_err(|e| {
+                    let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+                    Error::Syntax {
+                        line: index + 1,
+                        column: line[..offset].chars().count() + 1,
+                        token: token.to_string(),
+                        error: Box::new(e),
+                    }
+                })?;
+                fcidr.union(cidr);
+            }
+        }
+        Ok(fcidr)
+    }
 }
 
 impl From<Cidr> for Fcidr {
@@ -180,20 +822,80 @@ impl From<Cidr> for Fcidr {
     }
 }
 
+/// Unlike collecting into an [`Fcidr`], fails if any two of the CIDRs overlap.
+impl TryFrom<&[Cidr]> for Fcidr {
+    type Error = Error;
+
+    fn try_from(value: &[Cidr]) -> Result<Self, Self::Error> {
+        let mut sorted = value.to_vec();
+        sorted.sort();
+        // Sorted, a CIDR is preceded by any CIDR containing it.
+        let mut cover: Option<Cidr> = None;
+        for cidr in sorted {
+            match cover {
+                Some(cover) if cover.contains(cidr) => {
+                    return Err(Error::Conflict(format!("{cidr} overlaps {cover}")));
+                }
+                _ => cover = Some(cidr),
+            }
+        }
+        Ok(value.iter().copied().collect())
+    }
+}
+
+impl From<&Fcidr> for Vec<Cidr> {
+    fn from(value: &Fcidr) -> Self {
+        value.to_vec()
+    }
+}
+
+impl Extend<Cidr> for Fcidr {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(blocks, nodes))
+    )]
+    fn extend<T: IntoIterator<Item = Cidr>>(&mut self, iter: T) {
+        #[cfg(not(feature = "tracing"))]
+        for cidr in iter {
+            self.insert(cidr);
+        }
+        #[cfg(feature = "tracing")]
+        {
+            let (blocks, nodes) = iter.into_iter().fold((0, 0), |(blocks, nodes), cidr| {
+                (blocks + 1, nodes + self.insert(cidr))
+            });
+            let span = tracing::Span::current();
+            span.record("blocks", blocks);
+            span.record("nodes", nodes);
+        }
+    }
+}
+
+impl FromIterator<Cidr> for Fcidr {
+    fn from_iter<T: IntoIterator<Item = Cidr>>(iter: T) -> Self {
+        let mut fcidr = Self::default();
+        fcidr.extend(iter);
+        fcidr
+    }
+}
+
 impl IntoIterator for Fcidr {
     type Item = Cidr;
 
     type IntoIter = FcidrIntoIterator;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.iter()
+        FcidrIntoIterator {
+            tree: self.tree,
+            next: vec![ROOT],
+        }
     }
 }
 
-impl IntoIterator for &Fcidr {
+impl<'a> IntoIterator for &'a Fcidr {
     type Item = Cidr;
 
-    type IntoIter = FcidrIntoIterator;
+    type IntoIter = FcidrIter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
@@ -202,94 +904,677 @@ impl IntoIterator for &Fcidr {
 
 #[derive(Debug, Default)]
 pub struct FcidrIntoIterator {
-    next: Vec<Rc<RefCell<CidrNode>>>,
+    tree: Arc<Tree>,
+    next: Vec<NodeId>,
 }
 
 impl Iterator for FcidrIntoIterator {
     type Item = Cidr;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(node) = self.next.pop() {
-            match &node.borrow().inclusion {
-                Inclusion::Excluded => continue,
-                Inclusion::Included => return Some(node.borrow().cidr),
-                Inclusion::Subnets(subnets) => {
-                    for subnet in subnets.iter().rev().map(|s| s.to_owned()) {
-                        self.next.push(subnet);
-                    }
-                }
-            }
-        }
-        None
+        self.tree.walk(&mut self.next)
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-
-//     // #[test]
-//     // fn does_it_work() {
-//     //     let mut fcidr = Fcidr::default();
-//     //     fcidr.iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     fcidr.complement().complement().iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     // println!("{fcidr:#?}\n");
-//     //     println!();
-//     //     let mut fcidr = Fcidr::new("0.0.0.0/0".parse().unwrap());
-//     //     fcidr.iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     fcidr.complement().iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     // println!("{fcidr:#?}\n");
-//     //     println!();
-//     //     let mut fcidr = Fcidr::new("48.0.0.0/4".parse().unwrap());
-//     //     fcidr.iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     fcidr.complement().iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     // println!("{fcidr:#?}\n");
-//     //     println!();
-//     //     let mut fcidr = Fcidr::new("10.0.128.0/25".parse().unwrap());
-//     //     fcidr.iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     fcidr.complement().iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     // println!("{fcidr:#?}\n");
-//     //     println!();
-//     //     let mut fcidr = Fcidr::new("255.255.255.255/32".parse().unwrap());
-//     //     fcidr.iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     fcidr.complement().iter().for_each(|c| println!("{c}"));
-//     //     println!();
-//     //     // println!("{fcidr:#?}\n");
-//     //     println!();
-//     // }
-
-//     #[test]
-//     fn it_works() {
-//         let mut fcidr = Fcidr::default();
-//         fcidr.union("10.0.0.0/8".parse().unwrap());
-//         fcidr.union("10.0.128.0/24".parse().unwrap());
-//         fcidr.difference("10.0.80.0/20".parse().unwrap());
-//         fcidr.union("10.0.82.0/24".parse().unwrap());
-//         // fcidr.union("10.0.0.0/24".parse().unwrap());
-//         // fcidr.union("10.0.128.0/25".parse().unwrap());
-//         // fcidr.union("11.0.0.0/8".parse().unwrap());
-//         // fcidr.difference("10.0.0.64/32".parse().unwrap());
-//         // fcidr.union("10.0.0.64/32".parse().unwrap());
-//         // fcidr.difference("10.0.0.64/32".parse().unwrap());
-//         // fcidr.union("0.0.0.0/0".parse().unwrap());
-//         // fcidr.difference("128.0.0.0/32".parse().unwrap());
-//         // fcidr
-//         //     .difference("255.255.255.255/32".parse().unwrap());
-//         // fcidr.union("0.0.0.0/0".parse().unwrap());
-//         // fcidr.difference("10.0.0.1/32".parse().unwrap());
-//         // println!("{:?}", fcidr.iter().collect::<Vec<_>>());
-//         for cidr in &fcidr {
-//             println!("{cidr}");
-//         }
-//         println!("{fcidr:?}");
-//     }
-// }
+#[derive(Clone, Debug)]
+pub struct FcidrIter<'a> {
+    tree: &'a Tree,
+    next: Vec<NodeId>,
+}
+
+impl Iterator for FcidrIter<'_> {
+    type Item = Cidr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.tree.walk(&mut self.next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn union_range() {
+        let mut fcidr = Fcidr::new("10.0.0.0/24".parse().unwrap());
+        fcidr
+            .union_range("10.0.1.0".parse().unwrap(), "10.0.1.254".parse().unwrap())
+            .union_range("10.0.1.255".parse().unwrap(), "10.0.2.0".parse().unwrap())
+            .union_range("10.0.9.0".parse().unwrap(), "10.0.8.0".parse().unwrap());
+        assert_eq!(
+            fcidr
+                .iter()
+                .map(|cidr| cidr.to_string())
+                .collect::<Vec<_>>(),
+            ["10.0.0.0/23", "10.0.2.0/32"]
+        );
+    }
+
+    #[test]
+    fn iter_from() {
+        let fcidr = [
+            "10.0.0.0/24",
+            "10.0.2.0/23",
+            "10.1.0.0/16",
+            "192.168.0.0/16",
+        ]
+        .iter()
+        .map(|cidr| cidr.parse::<Cidr>().unwrap())
+        .collect::<Fcidr>();
+        let from = |ip: &str| {
+            fcidr
+                .iter_from(ip.parse().unwrap())
+                .map(|cidr| cidr.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            from("0.0.0.0"),
+            fcidr.iter().map(|c| c.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            from("10.0.0.255"),
+            [
+                "10.0.0.0/24",
+                "10.0.2.0/23",
+                "10.1.0.0/16",
+                "192.168.0.0/16"
+            ]
+        );
+        assert_eq!(
+            from("10.0.1.0"),
+            ["10.0.2.0/23", "10.1.0.0/16", "192.168.0.0/16"]
+        );
+        assert_eq!(
+            from("10.0.3.7"),
+            ["10.0.2.0/23", "10.1.0.0/16", "192.168.0.0/16"]
+        );
+        assert_eq!(from("10.2.0.0"), ["192.168.0.0/16"]);
+        assert!(from("192.169.0.0").is_empty());
+        assert_eq!(
+            Fcidr::new(Cidr::default())
+                .iter_from("1.2.3.4".parse().unwrap())
+                .count(),
+            1
+        );
+        assert_eq!(
+            Fcidr::default()
+                .iter_from("1.2.3.4".parse().unwrap())
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn histogram() {
+        let mut fcidr = Fcidr::new("10.0.0.0/7".parse().unwrap());
+        fcidr
+            .difference("10.0.0.0/9".parse().unwrap())
+            .union("192.168.1.0/24".parse().unwrap())
+            .union("192.168.3.0/25".parse().unwrap());
+        assert_eq!(
+            fcidr.histogram(8).unwrap(),
+            vec![
+                ("10.0.0.0/8".parse().unwrap(), 1 << 23),
+                ("11.0.0.0/8".parse().unwrap(), 1 << 24),
+                ("192.0.0.0/8".parse().unwrap(), 384),
+            ]
+        );
+        assert_eq!(
+            fcidr.histogram(0).unwrap(),
+            vec![(Cidr::default(), (3 << 23) + 384)]
+        );
+        assert_eq!(fcidr.histogram(9).unwrap().len(), 4);
+        assert!(matches!(fcidr.histogram(33), Err(Error::InvalidPrefix(_))));
+        let mut prefixes = [0; 33];
+        prefixes[8] = 1;
+        prefixes[9] = 1;
+        prefixes[24] = 1;
+        prefixes[25] = 1;
+        assert_eq!(fcidr.prefix_histogram(), prefixes);
+        assert_eq!(Fcidr::new(Cidr::default()).coverage(), 1.0);
+        assert_eq!(Fcidr::new("128.0.0.0/2".parse().unwrap()).coverage(), 0.25);
+        assert_eq!(Fcidr::default().coverage(), 0.0);
+        assert_eq!(
+            fcidr.covered_fraction(Some("10.0.0.0/8".parse().unwrap())),
+            0.5
+        );
+        assert_eq!(
+            fcidr.covered_fraction(Some("192.168.3.0/24".parse().unwrap())),
+            0.5
+        );
+        assert_eq!(
+            fcidr.covered_fraction(Some("192.168.1.1/32".parse().unwrap())),
+            1.0
+        );
+        assert_eq!(fcidr.covered_fraction(None), fcidr.coverage());
+    }
+
+    #[test]
+    fn require_superset() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr
+            .difference("10.0.64.0/20".parse().unwrap())
+            .union("10.0.66.0/24".parse().unwrap());
+        assert!(fcidr
+            .require_superset("10.0.0.0/16".parse().unwrap())
+            .is_err());
+        assert!(fcidr
+            .require_superset("10.0.66.0/24".parse().unwrap())
+            .is_ok());
+        let error = fcidr
+            .require_superset("10.0.0.0/7".parse().unwrap())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "not a superset of 10.0.0.0/7");
+        assert_eq!(
+            error.remainder().iter().collect::<Vec<_>>(),
+            vec![
+                "10.0.64.0/23".parse().unwrap(),
+                "10.0.67.0/24".parse().unwrap(),
+                "10.0.68.0/22".parse().unwrap(),
+                "10.0.72.0/21".parse().unwrap(),
+                "11.0.0.0/8".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn get() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr
+            .difference("10.0.64.0/20".parse().unwrap())
+            .union("10.0.82.0/24".parse().unwrap())
+            .union("192.168.0.0/16".parse().unwrap());
+        let cidrs = fcidr.iter().collect::<Vec<_>>();
+        for (index, cidr) in cidrs.iter().enumerate() {
+            assert_eq!(fcidr.get(index), Some(*cidr));
+        }
+        assert_eq!(fcidr.get(cidrs.len()), None);
+        fcidr.complement();
+        assert_eq!(fcidr.get(0), Some("0.0.0.0/5".parse().unwrap()));
+        assert_eq!(fcidr.get(fcidr.iter().count()), None);
+        fcidr.complement();
+        assert_eq!(fcidr.get(cidrs.len() - 1), cidrs.last().copied());
+        assert_eq!(Fcidr::default().get(0), None);
+    }
+
+    #[test]
+    fn from_ranges() {
+        let fcidr = Fcidr::from_ranges([
+            (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 1, 0)),
+            (
+                Ipv4Addr::new(192, 168, 0, 0),
+                Ipv4Addr::new(192, 168, 255, 255),
+            ),
+            (
+                Ipv4Addr::new(255, 255, 255, 254),
+                Ipv4Addr::new(255, 255, 255, 255),
+            ),
+            (Ipv4Addr::new(1, 0, 0, 1), Ipv4Addr::new(1, 0, 0, 0)),
+        ]);
+        assert_eq!(
+            fcidr.to_vec(),
+            vec![
+                "10.0.0.1/32".parse().unwrap(),
+                "10.0.0.2/31".parse().unwrap(),
+                "10.0.0.4/30".parse().unwrap(),
+                "10.0.0.8/29".parse().unwrap(),
+                "10.0.0.16/28".parse().unwrap(),
+                "10.0.0.32/27".parse().unwrap(),
+                "10.0.0.64/26".parse().unwrap(),
+                "10.0.0.128/25".parse().unwrap(),
+                "10.0.1.0/32".parse().unwrap(),
+                "192.168.0.0/16".parse().unwrap(),
+                "255.255.255.254/31".parse().unwrap(),
+            ]
+        );
+        assert_eq!(
+            Fcidr::from_ranges([(Ipv4Addr::UNSPECIFIED, Ipv4Addr::BROADCAST)]).to_vec(),
+            vec![Cidr::default()]
+        );
+        assert_eq!(
+            Fcidr::from_ranges(
+                [Ipv4Addr::new(10, 0, 0, 0)..=Ipv4Addr::new(10, 0, 0, 2)]
+                    .map(std::ops::RangeInclusive::into_inner)
+            )
+            .to_vec(),
+            ["10.0.0.0/31", "10.0.0.2/32"]
+                .map(|cidr| cidr.parse::<Cidr>().unwrap())
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn restrict() {
+        let mut fcidr = Fcidr::default();
+        fcidr
+            .union("10.0.0.0/8".parse().unwrap())
+            .union("10.1.0.0/16".parse().unwrap())
+            .union("172.16.0.0/12".parse().unwrap())
+            .difference("10.1.2.0/24".parse().unwrap());
+        let before = fcidr.clone();
+        fcidr.restrict("10.1.0.0/16".parse().unwrap());
+        assert_eq!(
+            fcidr.to_vec(),
+            vec![
+                "10.1.0.0/23".parse().unwrap(),
+                "10.1.3.0/24".parse().unwrap(),
+                "10.1.4.0/22".parse().unwrap(),
+                "10.1.8.0/21".parse().unwrap(),
+                "10.1.16.0/20".parse().unwrap(),
+                "10.1.32.0/19".parse().unwrap(),
+                "10.1.64.0/18".parse().unwrap(),
+                "10.1.128.0/17".parse().unwrap(),
+            ]
+        );
+        assert_eq!(fcidr.get(7), Some("10.1.128.0/17".parse().unwrap()));
+        let mut fcidr = before;
+        fcidr.restrict("10.1.2.0/24".parse().unwrap());
+        assert!(fcidr.to_vec().is_empty());
+        let mut fcidr = Fcidr::new(Cidr::default());
+        fcidr.restrict("192.168.0.0/16".parse().unwrap());
+        assert_eq!(fcidr.to_vec(), vec!["192.168.0.0/16".parse().unwrap()]);
+    }
+
+    #[test]
+    fn split_at() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.union("192.168.0.0/16".parse().unwrap());
+        let (below, above) = fcidr.split_at("10.128.0.0".parse().unwrap());
+        assert_eq!(below.to_vec(), vec!["10.0.0.0/9".parse().unwrap()]);
+        assert_eq!(
+            above.to_vec(),
+            vec![
+                "10.128.0.0/9".parse().unwrap(),
+                "192.168.0.0/16".parse().unwrap(),
+            ]
+        );
+        let (below, above) = fcidr.split_at("10.0.0.3".parse().unwrap());
+        assert_eq!(
+            below.to_vec(),
+            vec![
+                "10.0.0.0/31".parse().unwrap(),
+                "10.0.0.2/32".parse().unwrap()
+            ]
+        );
+        assert_eq!(above.iter().count(), 24);
+        let (below, above) = fcidr.split_at(Ipv4Addr::UNSPECIFIED);
+        assert!(below.to_vec().is_empty());
+        assert_eq!(above.to_vec(), fcidr.to_vec());
+    }
+
+    #[test]
+    fn partition() {
+        let mut fcidr = Fcidr::new("8.8.8.0/24".parse().unwrap());
+        fcidr
+            .union("10.0.0.0/8".parse().unwrap())
+            .union("192.168.0.0/16".parse().unwrap());
+        let (private, public) = fcidr.partition(|cidr| Fcidr::rfc1918().is_superset(cidr));
+        assert_eq!(
+            private.to_vec(),
+            vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "192.168.0.0/16".parse().unwrap()
+            ]
+        );
+        assert_eq!(public.to_vec(), vec!["8.8.8.0/24".parse().unwrap()]);
+    }
+
+    #[test]
+    fn supernet() {
+        let mut fcidr = Fcidr::new("10.0.0.0/24".parse().unwrap());
+        assert_eq!(fcidr.supernet(), Some("10.0.0.0/24".parse().unwrap()));
+        fcidr.union("10.0.3.128/25".parse().unwrap());
+        assert_eq!(fcidr.supernet(), Some("10.0.0.0/22".parse().unwrap()));
+        fcidr.union("192.168.0.0/16".parse().unwrap());
+        assert_eq!(fcidr.supernet(), Some(Cidr::default()));
+        assert_eq!(Fcidr::default().supernet(), None);
+    }
+
+    #[test]
+    fn to_bitmap() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr
+            .difference("10.0.0.0/30".parse().unwrap())
+            .difference("10.0.0.17/32".parse().unwrap())
+            .union("192.168.0.0/16".parse().unwrap());
+        let bitmap = fcidr.to_bitmap("10.0.0.0/27".parse().unwrap());
+        assert_eq!(bitmap, vec![0b1111_0000, 0xff, 0b1111_1101, 0xff]);
+        assert_eq!(
+            fcidr.to_bitmap("192.168.0.0/15".parse().unwrap()).len(),
+            1 << 14
+        );
+        assert_eq!(fcidr.to_bitmap("10.0.0.0/31".parse().unwrap()), vec![0]);
+        assert_eq!(fcidr.to_bitmap("10.0.0.4/31".parse().unwrap()), vec![0b11]);
+        assert_eq!(fcidr.to_bitmap("11.0.0.0/24".parse().unwrap()), vec![0; 32]);
+        let universe = "10.0.0.0/27".parse().unwrap();
+        let mut restricted = fcidr.clone();
+        restricted.restrict(universe);
+        assert_eq!(
+            Fcidr::from_bitmap(universe, &bitmap).to_vec(),
+            restricted.to_vec()
+        );
+        assert_eq!(
+            Fcidr::from_bitmap("10.0.0.0/30".parse().unwrap(), &[0xff]).to_vec(),
+            vec!["10.0.0.0/30".parse().unwrap()]
+        );
+        assert_eq!(
+            Fcidr::from_bitmap("10.0.0.0/24".parse().unwrap(), &[0x0f, 0x01]).to_vec(),
+            vec![
+                "10.0.0.0/30".parse().unwrap(),
+                "10.0.0.8/32".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            Fcidr::from_bitmap(Cidr::default(), &[0xff; 4]).to_vec(),
+            vec!["0.0.0.0/27".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn num_addresses() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.difference("10.1.0.0/16".parse().unwrap());
+        assert_eq!(fcidr.num_addresses(), (1 << 24) - (1 << 16));
+        assert_eq!(fcidr.num_prefixes(), fcidr.iter().count());
+        assert_eq!(fcidr.num_prefixes(), 8);
+        fcidr.complement();
+        assert_eq!(fcidr.num_addresses(), (1 << 32) - (1 << 24) + (1 << 16));
+        fcidr.restrict("10.0.0.0/8".parse().unwrap());
+        assert_eq!(fcidr.num_addresses(), 1 << 16);
+        assert_eq!(fcidr.num_prefixes(), 1);
+        assert_eq!(Fcidr::new(Cidr::default()).num_addresses(), 1 << 32);
+        assert_eq!(Fcidr::default().num_addresses(), 0);
+        assert_eq!(Fcidr::default().num_prefixes(), 0);
+    }
+
+    #[test]
+    fn display() {
+        let fcidr: Fcidr = "10.0.0.0/9, 10.128.0.0/9\n 192.168.0.0/16,172.16.0.0/12"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            fcidr.to_string(),
+            "10.0.0.0/8\n172.16.0.0/12\n192.168.0.0/16"
+        );
+        assert_eq!(
+            format!("{fcidr:#}"),
+            "10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16"
+        );
+        assert_eq!(fcidr.to_string().parse::<Fcidr>().unwrap(), fcidr);
+        assert_eq!(format!("{fcidr:#}").parse::<Fcidr>().unwrap(), fcidr);
+        assert_eq!(Fcidr::default().to_string(), "");
+        assert_eq!("".parse::<Fcidr>().unwrap(), Fcidr::default());
+        assert_eq!(
+            "10.0.0.0/8\n  é, 10.0.0.1/8".parse::<Fcidr>(),
+            Err(Error::Syntax {
+                line: 2,
+                column: 3,
+                token: "é".to_string(),
+                error: Box::new(Error::Parse("missing network prefix delimiter".to_string())),
+            })
+        );
+    }
+
+    #[test]
+    fn eq() {
+        use std::collections::HashMap;
+
+        let mut split = Fcidr::default();
+        split
+            .union("10.0.0.0/9".parse().unwrap())
+            .union("10.128.0.0/9".parse().unwrap());
+        let mut carved = Fcidr::new("0.0.0.0/0".parse().unwrap());
+        carved
+            .difference("0.0.0.0/5".parse().unwrap())
+            .difference("8.0.0.0/7".parse().unwrap())
+            .difference("11.0.0.0/8".parse().unwrap())
+            .difference("12.0.0.0/6".parse().unwrap())
+            .difference("16.0.0.0/4".parse().unwrap())
+            .difference("32.0.0.0/3".parse().unwrap())
+            .difference("64.0.0.0/2".parse().unwrap())
+            .difference("128.0.0.0/1".parse().unwrap());
+        let whole = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        assert_eq!(split, whole);
+        assert_eq!(carved, whole);
+        let mut counts = HashMap::new();
+        for fcidr in [split, carved, whole] {
+            *counts.entry(fcidr).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 1);
+        assert!(
+            Fcidr::new("9.0.0.0/8".parse().unwrap()) < Fcidr::new("10.0.0.0/8".parse().unwrap())
+        );
+        assert_ne!(Fcidr::default(), Fcidr::new(Cidr::default()));
+    }
+
+    #[test]
+    fn arena() {
+        let mut fcidr = Fcidr::default();
+        for _ in 0..3 {
+            fcidr.union("10.1.2.3/32".parse().unwrap());
+            assert_eq!(fcidr.tree.nodes.len() - fcidr.tree.free.len(), 65);
+            fcidr.difference("10.1.2.3/32".parse().unwrap());
+            assert_eq!(fcidr.tree.nodes.len() - fcidr.tree.free.len(), 1);
+        }
+        assert_eq!(fcidr.tree.nodes.len(), 65);
+        let mut other = Fcidr::default();
+        other.union("10.0.0.0/8".parse().unwrap());
+        fcidr.union("10.0.0.0/9".parse().unwrap());
+        fcidr.union("10.128.0.0/9".parse().unwrap());
+        assert_eq!(fcidr, other);
+        assert_ne!(fcidr.tree.nodes.len(), other.tree.nodes.len());
+    }
+
+    #[test]
+    fn iter() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.difference("10.0.0.0/16".parse().unwrap());
+        let mut iter = fcidr.iter();
+        assert_eq!(iter.next(), Some("10.1.0.0/16".parse().unwrap()));
+        // Borrowing iterators walk the tree in place rather than sharing it.
+        assert_eq!(Arc::strong_count(&fcidr.tree), 1);
+        assert_eq!(iter.count(), 7);
+        assert_eq!(
+            fcidr.clone().into_iter().collect::<Vec<_>>(),
+            fcidr.iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn threads() {
+        let fcidr = Arc::new(Fcidr::new("10.0.0.0/8".parse().unwrap()));
+        let handles = (0..4u8)
+            .map(|i| {
+                let fcidr = fcidr.clone();
+                std::thread::spawn(move || fcidr.contains_ip(Ipv4Addr::new(10, i, 0, 0)))
+            })
+            .collect::<Vec<_>>();
+        assert!(handles.into_iter().all(|handle| handle.join().unwrap()));
+        let iter = Fcidr::clone(&fcidr).into_iter();
+        assert_eq!(std::thread::spawn(move || iter.count()).join().unwrap(), 1);
+    }
+
+    #[test]
+    fn clone() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        let snapshot = fcidr.clone();
+        fcidr.difference("10.1.0.0/16".parse().unwrap());
+        assert_eq!(snapshot.to_vec(), vec!["10.0.0.0/8".parse().unwrap()]);
+        assert!(!fcidr.is_superset("10.0.0.0/8".parse().unwrap()));
+        let mut copy = fcidr.clone();
+        copy.complement();
+        assert!(fcidr.is_superset("10.2.0.0/16".parse().unwrap()));
+        assert!(!copy.is_superset("10.2.0.0/16".parse().unwrap()));
+    }
+
+    #[test]
+    fn derived() {
+        let fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        let before = fcidr.to_vec();
+        let unioned = fcidr.unioned("11.0.0.0/8".parse().unwrap());
+        let differenced = unioned.differenced("10.0.0.0/9".parse().unwrap());
+        let complemented = differenced.complemented();
+        let toggled = differenced.symmetric_differenced("10.0.0.0/8".parse().unwrap());
+        assert_eq!(fcidr.to_vec(), before);
+        assert_eq!(unioned.to_vec(), vec!["10.0.0.0/7".parse().unwrap()]);
+        assert_eq!(
+            differenced.to_vec(),
+            vec![
+                "10.128.0.0/9".parse().unwrap(),
+                "11.0.0.0/8".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            toggled.to_vec(),
+            vec!["10.0.0.0/9".parse().unwrap(), "11.0.0.0/8".parse().unwrap()]
+        );
+        assert!(complemented.is_superset("10.0.0.0/9".parse().unwrap()));
+        assert!(!complemented.is_superset("11.0.0.0/32".parse().unwrap()));
+    }
+
+    #[test]
+    fn try_from() {
+        let cidrs = ["10.0.0.0/8", "11.0.0.0/8", "10.1.0.0/16"].map(|c| c.parse().unwrap());
+        assert_eq!(
+            Fcidr::try_from(&cidrs[..]),
+            Err(Error::Conflict(
+                "10.1.0.0/16 overlaps 10.0.0.0/8".to_string()
+            ))
+        );
+        assert_eq!(
+            Fcidr::try_from(&cidrs[..2]).unwrap().to_vec(),
+            vec!["10.0.0.0/7".parse().unwrap()]
+        );
+        assert_eq!(
+            Fcidr::try_from(&[cidrs[1], cidrs[1]][..]),
+            Err(Error::Conflict(
+                "11.0.0.0/8 overlaps 11.0.0.0/8".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn symmetric_difference() {
+        let mut fcidr = Fcidr::new("10.0.0.0/9".parse().unwrap());
+        fcidr.union("11.0.0.0/8".parse().unwrap());
+        fcidr.symmetric_difference("10.0.0.0/8".parse().unwrap());
+        assert_eq!(
+            fcidr.to_vec(),
+            ["10.128.0.0/9", "11.0.0.0/8"].map(|cidr| cidr.parse::<Cidr>().unwrap())
+        );
+        fcidr.symmetric_difference("10.0.0.0/8".parse().unwrap());
+        assert_eq!(
+            fcidr.to_vec(),
+            ["10.0.0.0/9", "11.0.0.0/8"].map(|cidr| cidr.parse::<Cidr>().unwrap())
+        );
+        fcidr.symmetric_difference(Cidr::default());
+        assert_eq!(
+            fcidr.iter().count(),
+            fcidr.complemented().complemented().iter().count()
+        );
+        assert!(!fcidr.is_superset("11.0.0.0/8".parse().unwrap()));
+        assert!(fcidr.is_superset("10.128.0.0/9".parse().unwrap()));
+        fcidr.symmetric_difference(Cidr::default());
+        assert_eq!(
+            fcidr.to_vec(),
+            ["10.0.0.0/9", "11.0.0.0/8"].map(|cidr| cidr.parse::<Cidr>().unwrap())
+        );
+    }
+
+    #[test]
+    fn inclusion_of() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.difference("10.1.0.0/16".parse().unwrap());
+        let inclusion_of = |cidr: &str| fcidr.inclusion_of(cidr.parse().unwrap());
+        assert_eq!(inclusion_of("10.0.0.0/16"), Inclusion::Included);
+        assert_eq!(inclusion_of("10.2.3.4/32"), Inclusion::Included);
+        assert_eq!(inclusion_of("10.1.2.0/24"), Inclusion::Excluded);
+        assert_eq!(inclusion_of("11.0.0.0/8"), Inclusion::Excluded);
+        assert_eq!(inclusion_of("10.0.0.0/8"), Inclusion::Partial);
+        assert_eq!(inclusion_of("10.0.0.0/15"), Inclusion::Partial);
+        assert_eq!(inclusion_of("0.0.0.0/0"), Inclusion::Partial);
+        assert_eq!(
+            Fcidr::default().inclusion_of(Cidr::default()),
+            Inclusion::Excluded
+        );
+        assert_eq!(
+            Fcidr::new(Cidr::default()).inclusion_of(Cidr::default()),
+            Inclusion::Included
+        );
+    }
+
+    #[test]
+    fn contains() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.difference("10.1.0.0/16".parse().unwrap());
+        assert!(fcidr.contains_ip(Ipv4Addr::new(10, 0, 0, 1)));
+        assert!(!fcidr.contains_ip(Ipv4Addr::new(10, 1, 0, 1)));
+        assert!(fcidr.contains("10.2.0.0/16".parse::<Cidr>().unwrap()));
+        assert!(!fcidr.contains("10.0.0.0/8".parse::<Cidr>().unwrap()));
+        assert!(!Fcidr::default().contains(Ipv4Addr::UNSPECIFIED));
+    }
+
+    #[test]
+    fn nearest() {
+        let mut fcidr = Fcidr::new("10.0.0.0/24".parse().unwrap());
+        fcidr
+            .union("10.0.2.0/24".parse().unwrap())
+            .union("192.168.0.0/16".parse().unwrap());
+        let nearest = |ip: &str| fcidr.nearest(ip.parse().unwrap()).map(|ip| ip.to_string());
+        assert_eq!(nearest("10.0.0.7").as_deref(), Some("10.0.0.7"));
+        assert_eq!(nearest("10.0.1.7").as_deref(), Some("10.0.0.255"));
+        assert_eq!(nearest("10.0.1.200").as_deref(), Some("10.0.2.0"));
+        assert_eq!(nearest("10.0.1.127").as_deref(), Some("10.0.0.255"));
+        assert_eq!(nearest("0.0.0.0").as_deref(), Some("10.0.0.0"));
+        assert_eq!(
+            nearest("255.255.255.255").as_deref(),
+            Some("192.168.255.255")
+        );
+        assert_eq!(Fcidr::default().nearest(Ipv4Addr::UNSPECIFIED), None);
+    }
+
+    #[test]
+    fn addresses() {
+        let mut fcidr = Fcidr::new("10.0.0.0/30".parse().unwrap());
+        fcidr
+            .difference("10.0.0.1/32".parse().unwrap())
+            .union("255.255.255.255/32".parse().unwrap());
+        assert_eq!(
+            fcidr.addresses_capped(4).unwrap().collect::<Vec<_>>(),
+            vec![
+                Ipv4Addr::new(10, 0, 0, 0),
+                Ipv4Addr::new(10, 0, 0, 2),
+                Ipv4Addr::new(10, 0, 0, 3),
+                Ipv4Addr::new(255, 255, 255, 255),
+            ]
+        );
+        assert!(fcidr.addresses_capped(3).is_err());
+        assert!(Fcidr::new(Cidr::default())
+            .addresses_capped(u32::MAX as u64)
+            .is_err());
+        assert_eq!(
+            Fcidr::new(Cidr::default()).addresses().nth(1 << 16),
+            Some(Ipv4Addr::new(0, 1, 0, 0))
+        );
+    }
+
+    #[test]
+    fn to_vec() {
+        let mut fcidr = Fcidr::new("10.0.0.0/30".parse().unwrap());
+        fcidr
+            .difference("10.0.0.1/32".parse().unwrap())
+            .union("255.255.255.255/32".parse().unwrap());
+        let cidrs = fcidr.to_vec();
+        assert_eq!(
+            cidrs,
+            vec![
+                "10.0.0.0/32".parse().unwrap(),
+                "10.0.0.2/31".parse().unwrap(),
+                "255.255.255.255/32".parse().unwrap()
+            ]
+        );
+        assert_eq!(cidrs.capacity(), 3);
+        assert_eq!(Vec::from(&fcidr), cidrs);
+        assert!(Fcidr::default().to_vec().is_empty());
+    }
+}
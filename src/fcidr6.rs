@@ -0,0 +1,110 @@
+use crate::{trie::Node, Cidr6};
+
+/// The IPv6 counterpart of [`Fcidr`](crate::Fcidr), flattening a set of
+/// [`Cidr6`] blocks over the 128-bit address space.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Fcidr6 {
+    root: Node<bool, Cidr6>,
+}
+
+impl Fcidr6 {
+    pub fn new(cidr: Cidr6) -> Self {
+        let mut fcidr = Self::default();
+        fcidr.union(cidr);
+        fcidr
+    }
+
+    pub fn complement(&mut self) -> &mut Self {
+        self.root
+            .update(Cidr6::default(), &mut |included| *included = !*included);
+        self
+    }
+
+    pub fn difference(&mut self, cidr: Cidr6) -> &mut Self {
+        self.root.update(cidr, &mut |included| *included = false);
+        self
+    }
+
+    /// Toggles the coverage of every address in the block.
+    pub fn symmetric_difference(&mut self, cidr: Cidr6) -> &mut Self {
+        self.root
+            .update(cidr, &mut |included| *included = !*included);
+        self
+    }
+
+    pub fn union(&mut self, cidr: Cidr6) -> &mut Self {
+        self.root.update(cidr, &mut |included| *included = true);
+        self
+    }
+
+    pub fn is_superset(&self, cidr: Cidr6) -> bool {
+        self.root.all(cidr, &|&included| included)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Cidr6> + '_ {
+        self.root
+            .iter()
+            .filter_map(|(cidr, &included)| included.then_some(cidr))
+    }
+}
+
+impl From<Cidr6> for Fcidr6 {
+    fn from(value: Cidr6) -> Self {
+        Self::new(value)
+    }
+}
+
+impl Extend<Cidr6> for Fcidr6 {
+    fn extend<T: IntoIterator<Item = Cidr6>>(&mut self, iter: T) {
+        for cidr in iter {
+            self.union(cidr);
+        }
+    }
+}
+
+impl FromIterator<Cidr6> for Fcidr6 {
+    fn from_iter<T: IntoIterator<Item = Cidr6>>(iter: T) -> Self {
+        let mut fcidr = Self::default();
+        fcidr.extend(iter);
+        fcidr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidrs(fcidr: &Fcidr6) -> Vec<String> {
+        fcidr.iter().map(|cidr| cidr.to_string()).collect()
+    }
+
+    #[test]
+    fn it_works() {
+        let mut fcidr = ["2001:db8::/33", "2001:db8:8000::/33", "2001:db9::/32"]
+            .iter()
+            .map(|cidr| cidr.parse::<Cidr6>().unwrap())
+            .collect::<Fcidr6>();
+        assert_eq!(cidrs(&fcidr), ["2001:db8::/31"]);
+        fcidr.difference("2001:db8:1::/48".parse().unwrap());
+        assert_eq!(fcidr.iter().count(), 17);
+        assert!(fcidr.is_superset("2001:db8:2::/48".parse().unwrap()));
+        assert!(!fcidr.is_superset("2001:db8::/32".parse().unwrap()));
+        assert!(!fcidr.is_superset("2001:db8:1::1/128".parse().unwrap()));
+        fcidr.union("2001:db8:1::/48".parse().unwrap());
+        assert_eq!(cidrs(&fcidr), ["2001:db8::/31"]);
+        fcidr.complement();
+        assert_eq!(fcidr.iter().count(), 31);
+        assert!(!fcidr.is_superset("2001:db8::1/128".parse().unwrap()));
+        assert!(fcidr.is_superset("8000::/1".parse().unwrap()));
+        fcidr.complement();
+        assert_eq!(fcidr, Fcidr6::new("2001:db8::/31".parse().unwrap()));
+        fcidr.symmetric_difference("2001:db8::/32".parse().unwrap());
+        assert_eq!(cidrs(&fcidr), ["2001:db9::/32"]);
+        fcidr.symmetric_difference("2001:db8::/30".parse().unwrap());
+        assert_eq!(cidrs(&fcidr), ["2001:db8::/32", "2001:dba::/31"]);
+        assert_eq!(
+            cidrs(&Fcidr6::new(Cidr6::default()).complement().clone()),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,161 @@
+#![cfg(feature = "ffi")]
+#![cfg_attr(doc_cfg, doc(cfg(feature = "ffi")))]
+
+use std::{
+    ffi::{c_char, c_int, c_void, CStr},
+    net::Ipv4Addr,
+};
+
+use crate::{Cidr, Fcidr};
+
+fn parse(cidr: *const c_char) -> Option<Cidr> {
+    if cidr.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(cidr) }.to_str().ok()?.parse().ok()
+}
+
+/// Creates a new empty set. The result must be released with `fcidr_free`.
+#[no_mangle]
+pub extern "C" fn fcidr_new() -> *mut Fcidr {
+    Box::into_raw(Box::default())
+}
+
+/// Releases a set created with `fcidr_new`.
+///
+/// # Safety
+///
+/// `fcidr` must be null or a pointer returned by `fcidr_new` which has not
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn fcidr_free(fcidr: *mut Fcidr) {
+    if !fcidr.is_null() {
+        drop(Box::from_raw(fcidr));
+    }
+}
+
+/// Complements the set in place. Returns 0 on success or -1 if `fcidr` is
+/// null.
+///
+/// # Safety
+///
+/// `fcidr` must be null or a valid pointer returned by `fcidr_new`.
+#[no_mangle]
+pub unsafe extern "C" fn fcidr_complement(fcidr: *mut Fcidr) -> c_int {
+    match fcidr.as_mut() {
+        Some(fcidr) => {
+            fcidr.complement();
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Removes the nul-terminated CIDR string `cidr` from the set. Returns 0 on
+/// success or -1 if either argument is null or `cidr` fails to parse.
+///
+/// # Safety
+///
+/// `fcidr` must be null or a valid pointer returned by `fcidr_new`, and
+/// `cidr` must be null or point to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fcidr_difference(fcidr: *mut Fcidr, cidr: *const c_char) -> c_int {
+    match (fcidr.as_mut(), parse(cidr)) {
+        (Some(fcidr), Some(cidr)) => {
+            fcidr.difference(cidr);
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Adds the nul-terminated CIDR string `cidr` to the set. Returns 0 on success
+/// or -1 if either argument is null or `cidr` fails to parse.
+///
+/// # Safety
+///
+/// `fcidr` must be null or a valid pointer returned by `fcidr_new`, and
+/// `cidr` must be null or point to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fcidr_union(fcidr: *mut Fcidr, cidr: *const c_char) -> c_int {
+    match (fcidr.as_mut(), parse(cidr)) {
+        (Some(fcidr), Some(cidr)) => {
+            fcidr.union(cidr);
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Returns whether the set contains the address `addr`, given in host byte
+/// order. A null `fcidr` contains nothing.
+///
+/// # Safety
+///
+/// `fcidr` must be null or a valid pointer returned by `fcidr_new`.
+#[no_mangle]
+pub unsafe extern "C" fn fcidr_contains(fcidr: *const Fcidr, addr: u32) -> bool {
+    fcidr
+        .as_ref()
+        .is_some_and(|fcidr| fcidr.is_superset(Ipv4Addr::from(addr).into()))
+}
+
+/// Invokes `callback` with the network address (in host byte order) and
+/// prefix of each block in the set, in ascending order, passing `user_data`
+/// through untouched. Returns the number of blocks visited, saturating at
+/// `INTPTR_MAX`, or -1 if `fcidr` is null.
+///
+/// # Safety
+///
+/// `fcidr` must be null or a valid pointer returned by `fcidr_new`, and
+/// `callback` must be safe to call with `user_data`.
+#[no_mangle]
+pub unsafe extern "C" fn fcidr_for_each(
+    fcidr: *const Fcidr,
+    callback: extern "C" fn(network: u32, prefix: u8, user_data: *mut c_void),
+    user_data: *mut c_void,
+) -> isize {
+    match fcidr.as_ref() {
+        Some(fcidr) => {
+            let mut count: isize = 0;
+            for cidr in fcidr {
+                callback(cidr.network().into(), cidr.prefix(), user_data);
+                count = count.saturating_add(1);
+            }
+            count
+        }
+        None => -1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn collect(network: u32, prefix: u8, user_data: *mut c_void) {
+        let cidrs = unsafe { &mut *(user_data as *mut Vec<Cidr>) };
+        cidrs.push(Cidr::new(network.into(), prefix).unwrap());
+    }
+
+    #[test]
+    fn it_works() {
+        let [slash8, slash9, invalid] = ["10.0.0.0/8", "10.128.0.0/9", "not a cidr"]
+            .map(|cidr| std::ffi::CString::new(cidr).unwrap());
+        unsafe {
+            let fcidr = fcidr_new();
+            assert_eq!(fcidr_union(fcidr, slash8.as_ptr()), 0);
+            assert_eq!(fcidr_difference(fcidr, slash9.as_ptr()), 0);
+            assert_eq!(fcidr_union(fcidr, invalid.as_ptr()), -1);
+            assert!(fcidr_contains(fcidr, u32::from(Ipv4Addr::new(10, 1, 2, 3))));
+            assert!(!fcidr_contains(
+                fcidr,
+                u32::from(Ipv4Addr::new(10, 129, 2, 3))
+            ));
+            let mut cidrs: Vec<Cidr> = Vec::new();
+            let count = fcidr_for_each(fcidr, collect, &mut cidrs as *mut _ as *mut c_void);
+            assert_eq!(count, 1);
+            assert_eq!(cidrs, vec!["10.0.0.0/9".parse().unwrap()]);
+            fcidr_free(fcidr);
+        }
+    }
+}
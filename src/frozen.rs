@@ -0,0 +1,261 @@
+use std::net::Ipv4Addr;
+
+#[cfg(feature = "rkyv")]
+use crate::Error;
+use crate::{Cidr, Fcidr};
+
+fn cidr(network: u32, prefix: u8) -> Cidr {
+    Cidr::new(Ipv4Addr::from(network), prefix).expect("frozen cidr")
+}
+
+fn position(networks: impl Fn(usize) -> u32, len: usize, cidr: Cidr) -> Option<usize> {
+    let first = u32::from(cidr.first());
+    let (mut lo, mut hi) = (0, len);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if networks(mid) <= first {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo.checked_sub(1)
+}
+
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)
+)]
+pub struct FrozenFcidr {
+    networks: Vec<u32>,
+    prefixes: Vec<u8>,
+}
+
+impl FrozenFcidr {
+    pub fn len(&self) -> usize {
+        self.networks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+
+    pub fn contains<T>(&self, net: T) -> bool
+    where
+        T: Into<Cidr>,
+    {
+        let net = net.into();
+        position(|i| self.networks[i], self.len(), net)
+            .is_some_and(|i| cidr(self.networks[i], self.prefixes[i]).contains(net))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Cidr> + '_ {
+        self.networks
+            .iter()
+            .zip(&self.prefixes)
+            .map(|(&network, &prefix)| cidr(network, prefix))
+    }
+}
+
+/// Like [`FrozenFcidr`], but borrowing `'static` data, as built at compile time
+/// by [`fcidr_static!`](crate::fcidr_static).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StaticFcidr {
+    networks: &'static [u32],
+    prefixes: &'static [u8],
+}
+
+impl StaticFcidr {
+    #[doc(hidden)]
+    pub const fn from_parts(networks: &'static [u32], prefixes: &'static [u8]) -> Self {
+        Self { networks, prefixes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.networks.is_empty()
+    }
+
+    pub fn contains<T>(&self, net: T) -> bool
+    where
+        T: Into<Cidr>,
+    {
+        let net = net.into();
+        position(|i| self.networks[i], self.networks.len(), net)
+            .is_some_and(|i| cidr(self.networks[i], self.prefixes[i]).contains(net))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Cidr> + '_ {
+        // The arrays are padded out with copies of the last block.
+        self.networks
+            .iter()
+            .zip(self.prefixes)
+            .enumerate()
+            .filter(|&(i, (network, _))| i == 0 || self.networks[i - 1] != *network)
+            .map(|(_, (&network, &prefix))| cidr(network, prefix))
+    }
+}
+
+impl From<&StaticFcidr> for Fcidr {
+    fn from(value: &StaticFcidr) -> Self {
+        value.iter().collect()
+    }
+}
+
+impl From<&StaticFcidr> for FrozenFcidr {
+    fn from(value: &StaticFcidr) -> Self {
+        let (networks, prefixes) = value
+            .iter()
+            .map(|cidr| (u32::from(cidr.network()), cidr.prefix()))
+            .unzip();
+        Self { networks, prefixes }
+    }
+}
+
+impl From<&Fcidr> for FrozenFcidr {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(blocks))
+    )]
+    fn from(value: &Fcidr) -> Self {
+        let (networks, prefixes): (Vec<_>, _) = value
+            .iter()
+            .map(|cidr| (u32::from(cidr.network()), cidr.prefix()))
+            .unzip();
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("blocks", networks.len());
+        Self { networks, prefixes }
+    }
+}
+
+impl From<&FrozenFcidr> for Fcidr {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(blocks = value.len()))
+    )]
+    fn from(value: &FrozenFcidr) -> Self {
+        value.iter().collect()
+    }
+}
+
+#[cfg(feature = "rkyv")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "rkyv")))]
+impl ArchivedFrozenFcidr {
+    /// Validates the archive, which may come from anywhere, returning the
+    /// first malformed or out of order block. The queries below never panic on
+    /// a malformed archive, but only answer meaningfully for a valid one.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.networks.len() != self.prefixes.len() {
+            return Err(Error::Parse(format!(
+                "{} networks but {} prefixes",
+                self.networks.len(),
+                self.prefixes.len()
+            )));
+        }
+        let mut previous: Option<Cidr> = None;
+        for cidr in self
+            .networks
+            .iter()
+            .zip(self.prefixes.iter())
+            .map(|(network, &prefix)| Cidr::new(Ipv4Addr::from(network.to_native()), prefix))
+        {
+            let cidr = cidr?;
+            if let Some(previous) = previous.filter(|previous| previous.last() >= cidr.first()) {
+                return Err(Error::InvalidNetwork(format!(
+                    "{cidr} does not follow {previous}"
+                )));
+            }
+            previous = Some(cidr);
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.networks.len().min(self.prefixes.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains<T>(&self, net: T) -> bool
+    where
+        T: Into<Cidr>,
+    {
+        let net = net.into();
+        position(|i| self.networks[i].to_native(), self.len(), net).is_some_and(|i| {
+            Cidr::new(
+                Ipv4Addr::from(self.networks[i].to_native()),
+                self.prefixes[i],
+            )
+            .is_ok_and(|cidr| cidr.contains(net))
+        })
+    }
+
+    /// Iterates over the blocks, skipping any that are malformed.
+    pub fn iter(&self) -> impl Iterator<Item = Cidr> + '_ {
+        self.networks
+            .iter()
+            .zip(self.prefixes.iter())
+            .filter_map(|(network, &prefix)| {
+                Cidr::new(Ipv4Addr::from(network.to_native()), prefix).ok()
+            })
+    }
+}
+
+#[cfg(all(test, feature = "rkyv"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.difference("10.0.64.0/20".parse().unwrap());
+        let frozen = FrozenFcidr::from(&fcidr);
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&frozen).unwrap();
+        let archived = rkyv::access::<ArchivedFrozenFcidr, rkyv::rancor::Error>(&bytes).unwrap();
+        assert_eq!(
+            archived.iter().collect::<Vec<_>>(),
+            fcidr.iter().collect::<Vec<_>>()
+        );
+        for ip in ["10.0.0.1", "10.0.63.255", "10.0.80.0", "10.255.255.255"] {
+            let ip: Ipv4Addr = ip.parse().unwrap();
+            assert!(frozen.contains(ip));
+            assert!(archived.contains(ip));
+        }
+        for ip in ["9.255.255.255", "10.0.64.0", "10.0.79.255", "11.0.0.0"] {
+            let ip: Ipv4Addr = ip.parse().unwrap();
+            assert!(!frozen.contains(ip));
+            assert!(!archived.contains(ip));
+        }
+        assert!(archived.contains("10.0.128.0/17".parse::<Cidr>().unwrap()));
+        assert!(!archived.contains("10.0.0.0/16".parse::<Cidr>().unwrap()));
+    }
+
+    #[test]
+    fn check() {
+        let frozen = FrozenFcidr::from(&Fcidr::new("10.0.0.0/8".parse().unwrap()));
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&frozen).unwrap();
+        let archived = rkyv::access::<ArchivedFrozenFcidr, rkyv::rancor::Error>(&bytes).unwrap();
+        assert_eq!(archived.check(), Ok(()));
+        for (networks, prefixes) in [
+            (vec![0x0a00_0000], vec![33]),
+            (vec![0x0a00_0001], vec![8]),
+            (vec![0x0a00_0000, 0x0a00_0000], vec![8, 16]),
+            (vec![0x0a00_0000], vec![]),
+        ] {
+            let frozen = FrozenFcidr { networks, prefixes };
+            let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&frozen).unwrap();
+            let archived =
+                rkyv::access::<ArchivedFrozenFcidr, rkyv::rancor::Error>(&bytes).unwrap();
+            assert!(archived.check().is_err());
+            // Queries on the malformed archive answer without panicking.
+            let _ = archived.contains(Ipv4Addr::new(10, 0, 0, 1));
+            assert!(archived.iter().count() <= archived.len());
+        }
+    }
+}
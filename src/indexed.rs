@@ -0,0 +1,164 @@
+use std::{collections::BTreeSet, net::Ipv4Addr, ops::Deref};
+
+use crate::{Cidr, Fcidr};
+
+/// A set which also keeps the blocks it was built from, before aggregation,
+/// so that [`IndexedFcidr::matches`] can tell which of them cover an address.
+#[derive(Clone, Debug, Default)]
+pub struct IndexedFcidr {
+    fcidr: Fcidr,
+    index: BTreeSet<Cidr>,
+}
+
+impl IndexedFcidr {
+    /// Starts the index with the blocks already in the set.
+    pub fn new(fcidr: Fcidr) -> Self {
+        let index = fcidr.iter().collect();
+        Self { fcidr, index }
+    }
+
+    /// Complements the set. None of the indexed blocks cover anything after,
+    /// so the complement's own blocks take their place.
+    pub fn complement(&mut self) -> &mut Self {
+        self.fcidr.complement();
+        self.index = self.fcidr.iter().collect();
+        self
+    }
+
+    /// Differences the set, dropping the indexed blocks within `cidr`. Those
+    /// only partly excluded are kept, as they still cover the rest.
+    pub fn difference(&mut self, cidr: Cidr) -> &mut Self {
+        let unindexed = self
+            .index
+            .range(cidr..=Cidr::from(cidr.last()))
+            .copied()
+            .collect::<Vec<_>>();
+        for c in &unindexed {
+            self.index.remove(c);
+        }
+        self.fcidr.difference(cidr);
+        self
+    }
+
+    /// Drops all coverage outside of the universe, along with the indexed
+    /// blocks outside of it.
+    pub fn restrict(&mut self, universe: Cidr) -> &mut Self {
+        self.index
+            .retain(|c| c.contains(universe) || universe.contains(*c));
+        self.fcidr.restrict(universe);
+        self
+    }
+
+    pub fn union(&mut self, cidr: Cidr) -> &mut Self {
+        self.index.insert(cidr);
+        self.fcidr.union(cidr);
+        self
+    }
+
+    /// Returns every indexed block covering the address, from the widest, or
+    /// none if the set does not cover it.
+    pub fn matches(&self, ip: Ipv4Addr) -> Vec<Cidr> {
+        if !self.fcidr.is_superset(ip.into()) {
+            return Vec::new();
+        }
+        (0..=u32::BITS as u8)
+            .filter_map(|prefix| self.index.get(&Cidr::truncate(ip, prefix)).copied())
+            .collect()
+    }
+
+    pub fn into_inner(self) -> Fcidr {
+        self.fcidr
+    }
+}
+
+impl Deref for IndexedFcidr {
+    type Target = Fcidr;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fcidr
+    }
+}
+
+impl Fcidr {
+    pub fn indexed(self) -> IndexedFcidr {
+        IndexedFcidr::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut fcidr = IndexedFcidr::default();
+        fcidr
+            .union("10.0.0.0/8".parse().unwrap())
+            .union("10.1.0.0/16".parse().unwrap())
+            .union("10.1.2.0/24".parse().unwrap())
+            .union("10.3.0.0/16".parse().unwrap())
+            .difference("10.3.0.0/16".parse().unwrap())
+            .difference("10.1.2.128/25".parse().unwrap());
+        assert_eq!(
+            fcidr.matches("10.1.2.3".parse().unwrap()),
+            vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "10.1.0.0/16".parse().unwrap(),
+                "10.1.2.0/24".parse().unwrap(),
+            ]
+        );
+        assert_eq!(
+            fcidr.matches("10.2.0.0".parse().unwrap()),
+            vec!["10.0.0.0/8".parse().unwrap()]
+        );
+        assert!(fcidr.matches("10.1.2.200".parse().unwrap()).is_empty());
+        assert!(fcidr.matches("10.3.0.1".parse().unwrap()).is_empty());
+        assert!(fcidr.matches("11.0.0.0".parse().unwrap()).is_empty());
+        assert_eq!(
+            Fcidr::new("10.0.0.0/8".parse().unwrap())
+                .indexed()
+                .matches("10.0.0.1".parse().unwrap()),
+            vec!["10.0.0.0/8".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn complement() {
+        let mut fcidr = Fcidr::new("128.0.0.0/1".parse().unwrap()).indexed();
+        fcidr.union("10.0.0.0/8".parse().unwrap()).complement();
+        assert_eq!(
+            fcidr.matches("11.0.0.1".parse().unwrap()),
+            vec!["11.0.0.0/8".parse().unwrap()]
+        );
+        assert!(fcidr.matches("10.0.0.1".parse().unwrap()).is_empty());
+        fcidr.complement();
+        assert_eq!(
+            fcidr.matches("10.0.0.1".parse().unwrap()),
+            vec!["10.0.0.0/8".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn restrict() {
+        let mut fcidr = IndexedFcidr::default();
+        fcidr
+            .union("10.0.0.0/8".parse().unwrap())
+            .union("10.1.0.0/16".parse().unwrap())
+            .union("10.1.1.0/24".parse().unwrap())
+            .union("172.16.0.0/12".parse().unwrap())
+            .restrict("10.1.0.0/16".parse().unwrap());
+        assert_eq!(
+            fcidr.matches("10.1.1.1".parse().unwrap()),
+            vec![
+                "10.0.0.0/8".parse().unwrap(),
+                "10.1.0.0/16".parse().unwrap(),
+                "10.1.1.0/24".parse().unwrap()
+            ]
+        );
+        assert!(fcidr.matches("172.16.0.1".parse().unwrap()).is_empty());
+        assert_eq!(
+            fcidr.into_inner().to_vec(),
+            vec!["10.1.0.0/16".parse().unwrap()]
+        );
+    }
+}
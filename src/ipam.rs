@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use crate::{Cidr, Error, Fcidr};
+
+#[derive(Clone, Debug, Default)]
+pub struct Ipam {
+    universe: Fcidr,
+    free: Fcidr,
+    allocations: BTreeMap<Cidr, String>,
+}
+
+impl Ipam {
+    pub fn new<T>(universe: T) -> Self
+    where
+        T: Into<Fcidr>,
+    {
+        let universe = universe.into();
+        let free = universe.clone();
+        Self {
+            universe,
+            free,
+            allocations: BTreeMap::new(),
+        }
+    }
+
+    pub fn universe(&self) -> &Fcidr {
+        &self.universe
+    }
+
+    pub fn free(&self) -> &Fcidr {
+        &self.free
+    }
+
+    pub fn allocations(&self) -> impl Iterator<Item = (Cidr, &str)> + '_ {
+        self.allocations
+            .iter()
+            .map(|(cidr, label)| (*cidr, label.as_str()))
+    }
+
+    pub fn get(&self, cidr: Cidr) -> Option<&str> {
+        self.allocations.get(&cidr).map(String::as_str)
+    }
+
+    pub fn conflicts(&self, cidr: Cidr) -> Vec<(Cidr, &str)> {
+        self.allocations()
+            .filter(|(allocation, _)| allocation.contains(cidr) || cidr.contains(*allocation))
+            .collect()
+    }
+
+    pub fn allocate<S>(&mut self, prefix: u8, label: S) -> Result<Cidr, Error>
+    where
+        S: Into<String>,
+    {
+        if prefix as u32 > u32::BITS {
+            return Err(Error::InvalidPrefix(format!(
+                "network prefix '{prefix}' must be 32 or less"
+            )));
+        }
+        let block = self
+            .free
+            .iter()
+            .filter(|block| block.prefix() <= prefix)
+            .max_by_key(|block| (block.prefix(), std::cmp::Reverse(block.network())))
+            .ok_or_else(|| Error::Exhausted(format!("no free /{prefix} remains")))?;
+        let cidr = Cidr::new(block.network(), prefix)?;
+        self.free.difference(cidr);
+        self.allocations.insert(cidr, label.into());
+        Ok(cidr)
+    }
+
+    pub fn reserve<S>(&mut self, cidr: Cidr, label: S) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        if !self.universe.is_superset(cidr) {
+            return Err(Error::Conflict(format!(
+                "{cidr} is outside of the universe"
+            )));
+        }
+        if let Some((allocation, label)) = self.conflicts(cidr).first() {
+            return Err(Error::Conflict(format!(
+                "{cidr} overlaps {allocation} allocated to '{label}'"
+            )));
+        }
+        self.free.difference(cidr);
+        self.allocations.insert(cidr, label.into());
+        Ok(())
+    }
+
+    pub fn release(&mut self, cidr: Cidr) -> Result<String, Error> {
+        let label = self
+            .allocations
+            .remove(&cidr)
+            .ok_or_else(|| Error::NotFound(format!("{cidr} is not allocated")))?;
+        self.free.union(cidr);
+        Ok(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut ipam = Ipam::new("10.0.0.0/22".parse::<Cidr>().unwrap());
+        ipam.reserve("10.0.1.0/24".parse().unwrap(), "gateway")
+            .unwrap();
+        assert_eq!(
+            ipam.allocate(25, "web").unwrap(),
+            "10.0.0.0/25".parse().unwrap()
+        );
+        assert_eq!(
+            ipam.allocate(26, "db").unwrap(),
+            "10.0.0.128/26".parse().unwrap()
+        );
+        assert_eq!(
+            ipam.allocate(23, "k8s").unwrap(),
+            "10.0.2.0/23".parse().unwrap()
+        );
+        assert!(matches!(ipam.allocate(25, "x"), Err(Error::Exhausted(_))));
+        assert!(matches!(
+            ipam.reserve("10.0.1.128/25".parse().unwrap(), "x"),
+            Err(Error::Conflict(_))
+        ));
+        assert!(matches!(
+            ipam.reserve("10.0.4.0/24".parse().unwrap(), "x"),
+            Err(Error::Conflict(_))
+        ));
+        assert_eq!(ipam.release("10.0.2.0/23".parse().unwrap()).unwrap(), "k8s");
+        assert!(matches!(
+            ipam.release("10.0.2.0/23".parse().unwrap()),
+            Err(Error::NotFound(_))
+        ));
+        assert_eq!(
+            ipam.allocate(26, "cache").unwrap(),
+            "10.0.0.192/26".parse().unwrap()
+        );
+        assert_eq!(ipam.get("10.0.0.0/25".parse().unwrap()), Some("web"));
+        assert_eq!(ipam.allocations().count(), 4);
+    }
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "iprange")]
+#![cfg_attr(doc_cfg, doc(cfg(feature = "iprange")))]
+
+use ::ipnet::Ipv4Net;
+use ::iprange::IpRange;
+
+use crate::{Cidr, Fcidr};
+
+impl From<Cidr> for Ipv4Net {
+    fn from(value: Cidr) -> Self {
+        Self::new(value.network(), value.prefix()).expect("convert from Cidr")
+    }
+}
+
+impl From<Ipv4Net> for Cidr {
+    fn from(value: Ipv4Net) -> Self {
+        Self::new(value.network(), value.prefix_len()).expect("convert from Ipv4Net")
+    }
+}
+
+impl From<Fcidr> for IpRange<Ipv4Net> {
+    fn from(value: Fcidr) -> Self {
+        value.iter().map(Ipv4Net::from).collect()
+    }
+}
+
+impl From<IpRange<Ipv4Net>> for Fcidr {
+    fn from(value: IpRange<Ipv4Net>) -> Self {
+        let mut fcidr = Self::default();
+        for net in &value {
+            fcidr.union(net.into());
+        }
+        fcidr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.difference("10.0.64.0/20".parse().unwrap());
+        let ip_range = IpRange::<Ipv4Net>::from(fcidr.clone());
+        let mut cidrs = ip_range.iter().map(Cidr::from).collect::<Vec<_>>();
+        cidrs.sort();
+        assert_eq!(cidrs, fcidr.iter().collect::<Vec<_>>());
+        assert_eq!(
+            Fcidr::from(ip_range).iter().collect::<Vec<_>>(),
+            fcidr.iter().collect::<Vec<_>>()
+        );
+        let net: Ipv4Net = "10.1.2.3/16".parse().unwrap();
+        assert_eq!(Cidr::from(net), "10.1.0.0/16".parse().unwrap());
+    }
+}
@@ -1,8 +1,56 @@
+mod checkpoint;
 mod cidr;
+mod cidr6;
+mod cidr_crate;
+mod diesel;
+mod dual;
+mod embedded;
 mod error;
+pub mod exit;
 mod fcidr;
-mod serde;
+mod fcidr6;
+mod ffi;
+mod frozen;
+mod indexed;
+mod ipam;
+mod iprange;
+mod map;
+mod multiset;
+mod nat64;
+mod observer;
+mod ops;
+pub mod proto;
+mod rules;
+mod rusqlite;
+pub mod serde;
+mod sqlx;
+pub mod stream;
+mod tagged;
+mod transaction;
+mod trie;
+mod wasm;
+mod well_known;
 
-pub use crate::cidr::Cidr;
-pub use crate::error::Error;
-pub use crate::fcidr::Fcidr;
+pub use crate::checkpoint::CheckpointedFcidr;
+pub use crate::cidr::{Cidr, CidrIntoIterator};
+pub use crate::cidr6::Cidr6;
+pub use crate::dual::{DualFcidr, IpCidr};
+pub use crate::error::{Error, NotSupersetError};
+pub use crate::fcidr::{Fcidr, FcidrIntoIterator, FcidrIter, Inclusion};
+pub use crate::fcidr6::Fcidr6;
+#[cfg(feature = "rkyv")]
+pub use crate::frozen::ArchivedFrozenFcidr;
+pub use crate::frozen::{FrozenFcidr, StaticFcidr};
+pub use crate::indexed::IndexedFcidr;
+pub use crate::ipam::Ipam;
+pub use crate::map::{FcidrMap, FcidrMapIter};
+pub use crate::multiset::FcidrMultiset;
+pub use crate::observer::{Change, ObservedFcidr};
+pub use crate::rules::{Action, RuleSet};
+pub use crate::tagged::TaggedFcidr;
+pub use crate::transaction::Transaction;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::embedded::{count, parse, parse_cidr};
+}
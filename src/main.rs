@@ -1,27 +1,296 @@
 use std::{
     error::Error,
-    io::{stdin, IsTerminal},
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, stdin, stdout, BufRead, BufReader, BufWriter, IsTerminal, Write},
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use clap::{CommandFactory, Parser, Subcommand};
-use fcidr::{Cidr, Fcidr};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use fcidr::{exit, Action, Cidr, Cidr6, DualFcidr, Fcidr, IpCidr, RuleSet};
+use serde::{Deserialize, Serialize, Serializer};
 
 #[derive(Debug, Parser)]
 #[command(about, author, version, long_about = None)]
 struct Cli {
     /// The input CIDR range and first operand to the computation. If omitted,
     /// input is taken from stdin. In this way, multiple computations can be
-    /// chained together.
-    cidr: Option<Cidr>,
+    /// chained together. IPv4 and IPv6 CIDRs may be mixed, both here and on
+    /// stdin, though only the set operations accept IPv6. Bare addresses are
+    /// read as host blocks, here and everywhere else.
+    cidr: Option<String>,
+    /// The format in which to read input CIDRs from stdin
+    #[arg(long, value_enum, default_value_t, global = true)]
+    input_format: Format,
+    /// The format in which to write the resulting CIDRs to stdout
+    #[arg(long, value_enum, default_value_t, global = true)]
+    format: Format,
+    #[command(flatten)]
+    csv: Csv,
+    #[command(flatten)]
+    order: Order,
+    /// Write the resulting CIDRs into one file per shard in this directory
+    /// instead of to stdout
+    #[arg(long, global = true)]
+    output_dir: Option<PathBuf>,
+    /// The prefix length of the blocks by which to shard output, a multiple of
+    /// 8 [default: /8]
+    #[arg(long, value_parser = parse_shard_prefix, requires = "output_dir", global = true)]
+    shard_by: Option<u8>,
+    /// The format in which to write diagnostics to stderr
+    #[arg(
+        long,
+        visible_alias = "errors",
+        value_enum,
+        default_value_t,
+        global = true
+    )]
+    log_format: LogFormat,
+    /// Write a one-line summary of the blocks read and written, the addresses
+    /// they cover and the time taken to stderr when done
+    #[arg(long, global = true)]
+    summary: bool,
+    /// Clear the host bits of input CIDRs such as 10.1.2.3/24 rather than
+    /// rejecting them
+    #[arg(long, global = true)]
+    truncate: bool,
     #[command(subcommand)]
     command: FcidrCommand,
 }
 
+/// How to write only the size of a resulting set, for the subcommands writing
+/// one.
+#[derive(Args, Clone, Copy, Debug, Default)]
+struct Count {
+    /// Write only the number of resulting CIDRs
+    #[arg(short, long, conflicts_with = "output_dir")]
+    count: bool,
+    /// With --count, write the number of addresses covered by the resulting
+    /// CIDRs instead
+    #[arg(long, requires = "count")]
+    addresses: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    /// One CIDR per line
+    #[default]
+    Text,
+    /// A single JSON array of CIDR strings
+    Json,
+    /// One JSON string per line (JSON Lines)
+    Jsonl,
+    /// One CIDR per row in a column of a CSV table with a header row
+    Csv,
+}
+
+#[derive(Args, Clone, Copy, Debug)]
+struct Order {
+    /// The order in which to write the resulting CIDRs
+    #[arg(long, value_enum, default_value_t, global = true)]
+    sort_by: SortBy,
+    /// Write the resulting CIDRs in descending rather than ascending order
+    #[arg(long, global = true)]
+    reverse: bool,
+}
+
+impl Order {
+    fn sort(&self, cidrs: impl IntoIterator<Item = impl Into<IpCidr>>) -> Vec<IpCidr> {
+        let mut cidrs = cidrs.into_iter().map(Into::into).collect::<Vec<_>>();
+        // Stable, so that blocks of equal prefix length stay in address order.
+        cidrs.sort_by(|a, b| {
+            let ordering = match self.sort_by {
+                SortBy::Address => a.cmp(b),
+                SortBy::Prefix => prefix(a).cmp(&prefix(b)),
+                SortBy::Size => host_bits(a).cmp(&host_bits(b)),
+            };
+            if self.reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        cidrs
+    }
+}
+
+fn prefix(cidr: &IpCidr) -> u8 {
+    match cidr {
+        IpCidr::V4(cidr) => cidr.prefix(),
+        IpCidr::V6(cidr) => cidr.prefix(),
+    }
+}
+
+fn host_bits(cidr: &IpCidr) -> u32 {
+    match cidr {
+        IpCidr::V4(cidr) => u32::BITS - cidr.prefix() as u32,
+        IpCidr::V6(cidr) => u128::BITS - cidr.prefix() as u32,
+    }
+}
+
+/// The number of addresses in the block, saturating for ::/0.
+fn size(cidr: &IpCidr) -> u128 {
+    1u128.checked_shl(host_bits(cidr)).unwrap_or(u128::MAX)
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+enum SortBy {
+    /// By network address
+    #[default]
+    Address,
+    /// By prefix length, least specific first
+    Prefix,
+    /// By number of addresses, smallest first
+    Size,
+}
+
+#[derive(Args, Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Csv {
+    /// The column of CSV input holding the CIDRs, by header name or 1-based
+    /// index
+    #[arg(long, default_value = "1", global = true)]
+    column: String,
+    /// The field delimiter of CSV input
+    #[arg(long, default_value_t = ',', global = true)]
+    delimiter: char,
+}
+
+impl Default for Csv {
+    fn default() -> Self {
+        Self {
+            column: "1".to_string(),
+            delimiter: ',',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+enum LogFormat {
+    /// A human readable message
+    #[default]
+    Text,
+    /// One JSON object per diagnostic with its level, kind, message and input
+    /// line, if any, plus the column and token of malformed input
+    Json,
+}
+
+impl LogFormat {
+    /// Recovers the format from arguments that fail to parse, as far as clap
+    /// can still make it out, falling back to the default.
+    fn recover<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        Cli::command()
+            .ignore_errors(true)
+            .try_get_matches_from(args)
+            .ok()
+            .and_then(|matches| matches.get_one::<Self>("log_format").copied())
+            .unwrap_or_default()
+    }
+
+    fn error(&self, kind: &str, e: &(dyn Error + 'static), line: Option<usize>) {
+        eprintln!("{}", self.error_entry(kind, e, line))
+    }
+
+    /// Renders an error, giving the column and token of malformed input as
+    /// fields of their own in JSON.
+    fn error_entry(&self, kind: &str, e: &(dyn Error + 'static), line: Option<usize>) -> String {
+        match (self, e.downcast_ref::<fcidr::Error>()) {
+            (
+                Self::Json,
+                Some(fcidr::Error::Syntax {
+                    line,
+                    column,
+                    token,
+                    error,
+                }),
+            ) => serde_json::json!({
+                "level": "error",
+                "kind": kind,
+                "message": error.to_string(),
+                "line": line,
+                "column": column,
+                "token": token,
+            })
+            .to_string(),
+            _ => self.entry("error", kind, &e.to_string(), line),
+        }
+    }
+
+    fn warning(&self, kind: &str, message: &str, line: Option<usize>) {
+        self.log("warning", kind, message, line)
+    }
+
+    fn summary(&self, summary: &Summary, elapsed: Duration) {
+        eprintln!("{}", self.footer(summary, elapsed))
+    }
+
+    fn footer(&self, summary: &Summary, elapsed: Duration) -> String {
+        let Summary {
+            blocks_in,
+            blocks_out,
+            addresses,
+        } = summary;
+        match self {
+            Self::Text => format!(
+                "{blocks_in} block(s) in, {blocks_out} block(s) out, {addresses} address(es) covered in {elapsed:.2?}"
+            ),
+            Self::Json => serde_json::json!({
+                "level": "info",
+                "kind": "summary",
+                "blocks_in": blocks_in,
+                "blocks_out": blocks_out,
+                "addresses": addresses,
+                "elapsed_ms": elapsed.as_secs_f64() * 1000.0,
+            })
+            .to_string(),
+        }
+    }
+
+    fn log(&self, level: &str, kind: &str, message: &str, line: Option<usize>) {
+        eprintln!("{}", self.entry(level, kind, message, line))
+    }
+
+    fn entry(&self, level: &str, kind: &str, message: &str, line: Option<usize>) -> String {
+        match self {
+            Self::Text => {
+                let mut level = level.chars();
+                let first = level.next().map(|c| c.to_ascii_uppercase());
+                format!("{}{}: {message}", first.unwrap_or_default(), level.as_str())
+            }
+            Self::Json => serde_json::json!({
+                "level": level,
+                "kind": kind,
+                "message": message,
+                "line": line,
+            })
+            .to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum FcidrCommand {
     /// Compute the complement of the input CIDR(s)
     #[command(visible_alias = "!", visible_alias = "not")]
-    Complement,
+    Complement {
+        #[command(flatten)]
+        count: Count,
+    },
+    /// Report how the input CIDR(s) relate to other CIDRs, exiting with a
+    /// distinct code for each relationship
+    Compare {
+        /// CIDRs, or paths to files of CIDRs, making up the second operand
+        #[arg(required = true)]
+        operands: Vec<String>,
+    },
     /// Compute the set difference between the input CIDR(s) and another CIDR
     #[command(
         visible_alias = "-",
@@ -30,56 +299,2032 @@ enum FcidrCommand {
     )]
     Difference {
         /// The second CIDR range operand for the difference function
-        cidr: Cidr,
+        cidr: String,
+        #[command(flatten)]
+        count: Count,
+    },
+    /// Extract every IPv4 address and CIDR from free-form text on stdin, in
+    /// order of appearance
+    Grep {
+        /// Aggregate the extracted CIDRs into a set instead
+        #[arg(long)]
+        normalize: bool,
+        #[command(flatten)]
+        count: Count,
+    },
+    /// Evaluate an ordered, first match wins list of `allow <cidr>` and
+    /// `deny <cidr>` rules, reporting rules shadowed by earlier ones and
+    /// writing the addresses allowed
+    LintRules {
+        /// Path to the rules, one per line, with `#` starting a comment
+        rules: PathBuf,
+        #[command(flatten)]
+        count: Count,
+    },
+    /// Print the covered address closest to another address
+    Nearest { ip: Ipv4Addr },
+    /// Print free blocks of a given size, treating the input CIDR(s) as used
+    Pick {
+        /// The prefix length of the blocks to pick, e.g. /24
+        #[arg(long, value_parser = parse_prefix)]
+        size: u8,
+        /// How many blocks to pick
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        count: u64,
+        /// The CIDR range from which to pick blocks
+        #[arg(long, default_value_t)]
+        within: Cidr,
+        /// Pick blocks that are adjacent to each other
+        #[arg(long)]
+        contiguous: bool,
+    },
+    /// Report input CIDRs already covered by another input CIDR, exiting
+    /// unsuccessfully if there are any
+    Redundant,
+    /// Execute the declarative pipeline described by a YAML file
+    Run {
+        /// Path to the pipeline description
+        pipeline: PathBuf,
+    },
+    /// Compute the smallest single CIDR containing all of the input CIDR(s)
+    Supernet {
+        #[command(flatten)]
+        count: Count,
     },
     /// Exits successfully if the input CIDR(s) is a superset of another CIDR
     #[command(visible_alias = ">", visible_alias = "contains")]
-    Superset { cidr: Cidr },
+    Superset { cidr: String },
     /// Compute the set union of the input CIDR(s) and another CIDR
     #[command(visible_alias = "+", visible_alias = "include", visible_alias = "plus")]
     Union {
         /// The second CIDR range operand for the union function
-        cidr: Cidr,
+        cidr: String,
+        #[command(flatten)]
+        count: Count,
     },
+    /// Compute the symmetric difference of the input CIDR(s) and another CIDR,
+    /// toggling the coverage of every address within it
+    #[command(visible_alias = "^", visible_alias = "toggle")]
+    Xor {
+        /// The second CIDR range operand for the symmetric difference function
+        cidr: String,
+        #[command(flatten)]
+        count: Count,
+    },
+}
+
+impl FcidrCommand {
+    fn count(&self) -> Count {
+        match self {
+            Self::Complement { count }
+            | Self::Difference { count, .. }
+            | Self::Grep { count, .. }
+            | Self::LintRules { count, .. }
+            | Self::Supernet { count }
+            | Self::Union { count, .. }
+            | Self::Xor { count, .. } => *count,
+            _ => Count::default(),
+        }
+    }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let cli = Cli::parse();
+fn main() {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if e.use_stderr() => {
+            let log_format = LogFormat::recover(std::env::args_os());
+            if let LogFormat::Text = log_format {
+                e.exit();
+            }
+            let rendered = e.render().to_string();
+            let message = rendered.lines().next().unwrap_or_default();
+            log_format.log(
+                "error",
+                "usage",
+                message.trim_start_matches("error: "),
+                None,
+            );
+            ::std::process::exit(exit::USAGE);
+        }
+        Err(e) => e.exit(),
+    };
+    let log_format = cli.log_format;
+    let start = cli.summary.then(Instant::now);
+    let mut summary = Summary::default();
+    let reads_stdin = match &cli.command {
+        FcidrCommand::LintRules { .. } | FcidrCommand::Run { .. } => false,
+        FcidrCommand::Grep { .. } | FcidrCommand::Redundant => true,
+        _ => cli.cidr.is_none(),
+    };
+    if reads_stdin && stdin().is_terminal() {
+        Cli::command().print_help().unwrap();
+        ::std::process::exit(exit::USAGE);
+    }
+    let result = run(cli, stdin().lock(), stdout().lock(), &mut summary);
+    if let Some(start) = start {
+        log_format.summary(&summary, start.elapsed());
+    }
+    match result {
+        Ok(code) => ::std::process::exit(code),
+        Err(e) => {
+            let (kind, code, line) = classify(e.as_ref());
+            log_format.error(kind, e.as_ref(), line);
+            ::std::process::exit(code);
+        }
+    }
+}
+
+/// Tallies of the blocks read and written by a single invocation, for
+/// `--summary`.
+#[derive(Debug, Default)]
+struct Summary {
+    blocks_in: u64,
+    blocks_out: u64,
+    addresses: u128,
+}
 
-    let mut fcidr: Fcidr = if let Some(cidr) = cli.cidr {
-        Fcidr::new(cidr)
+impl Summary {
+    fn read(&mut self, blocks: u64) {
+        self.blocks_in += blocks;
+    }
+
+    fn wrote(&mut self, cidr: IpCidr) {
+        self.blocks_out += 1;
+        self.addresses = self.addresses.saturating_add(size(&cidr));
+    }
+}
+
+fn classify(e: &(dyn Error + 'static)) -> (&'static str, i32, Option<usize>) {
+    if e.is::<fcidr::NotSupersetError>() || e.is::<NotSupersetError6>() {
+        ("not_superset", exit::FAILURE, None)
+    } else if e.is::<RedundantError>() {
+        ("redundant", exit::FAILURE, None)
+    } else if e.is::<ShadowedError>() {
+        ("shadowed", exit::FAILURE, None)
+    } else if let Some(e) = e.downcast_ref::<fcidr::Error>() {
+        match e {
+            fcidr::Error::Exhausted(_) => ("exhausted", exit::FAILURE, None),
+            fcidr::Error::LimitExceeded(_) => ("limit_exceeded", exit::FAILURE, None),
+            fcidr::Error::NotFound(_) => ("not_found", exit::FAILURE, None),
+            fcidr::Error::Syntax { line, .. } => ("parse", exit::DATA, Some(*line)),
+            _ => ("parse", exit::DATA, None),
+        }
+    } else if let Some(e) = e.downcast_ref::<serde_json::Error>() {
+        match e.classify() {
+            serde_json::error::Category::Io => ("io", exit::IO, None),
+            _ => ("parse", exit::DATA, Some(e.line())),
+        }
+    } else if let Some(e) = e.downcast_ref::<csv::Error>() {
+        match e.kind() {
+            csv::ErrorKind::Io(_) => ("io", exit::IO, None),
+            _ => ("parse", exit::DATA, e.position().map(|p| p.line() as usize)),
+        }
+    } else if let Some(e) = e.downcast_ref::<serde_yaml::Error>() {
+        ("pipeline", exit::DATA, e.location().map(|l| l.line()))
+    } else if e.is::<io::Error>() || e.is::<ureq::Error>() {
+        ("io", exit::IO, None)
     } else {
-        if stdin().is_terminal() {
-            Cli::command().print_help().unwrap();
-            ::std::process::exit(2);
-        }
-        stdin().lines().fold(
-            Ok(Fcidr::default()),
-            |fcidr: Result<Fcidr, Box<dyn Error>>, l| {
-                if let Ok(mut fcidr) = fcidr {
-                    fcidr.union(l?.parse()?);
-                    return Ok(fcidr);
-                }
-                fcidr
-            },
-        )?
+        ("other", exit::FAILURE, None)
+    }
+}
+
+/// Runs the command, returning the code with which to exit.
+fn run(
+    cli: Cli,
+    input: impl BufRead,
+    mut output: impl Write,
+    summary: &mut Summary,
+) -> Result<i32, Box<dyn Error>> {
+    if let FcidrCommand::Run { pipeline } = &cli.command {
+        Pipeline::load(pipeline)?.run(cli.truncate, summary)?;
+        return Ok(exit::SUCCESS);
+    }
+
+    if let FcidrCommand::LintRules { rules, .. } = &cli.command {
+        lint_rules(
+            BufReader::new(File::open(rules)?),
+            &mut output,
+            &cli,
+            summary,
+        )?;
+        return Ok(exit::SUCCESS);
+    }
+
+    if let FcidrCommand::Grep { normalize, .. } = &cli.command {
+        let mut input = decompress(input)?;
+        let mut cidrs = Vec::new();
+        let mut line = Vec::new();
+        while input.read_until(b'\n', &mut line)? > 0 {
+            let extracted = extract(&String::from_utf8_lossy(&line), cli.truncate);
+            summary.read(extracted.len() as u64);
+            cidrs.extend(extracted);
+            line.clear();
+        }
+        let cidrs = if *normalize {
+            cli.order.sort(cidrs.into_iter().collect::<Fcidr>())
+        } else {
+            cidrs.into_iter().map(IpCidr::from).collect()
+        };
+        write_output(&mut output, cidrs, cli.command.count(), cli.format, summary)?;
+        return Ok(exit::SUCCESS);
+    }
+    if let FcidrCommand::Redundant = &cli.command {
+        redundant(input, &mut output, &cli, summary)?;
+        return Ok(exit::SUCCESS);
+    }
+
+    // Parsed here rather than by clap, so that --truncate applies to them.
+    let truncate = cli.truncate;
+    let operand = |cidr: &str| -> Result<IpCidr, fcidr::Error> { Token::parse(cidr, truncate) };
+    let mut dual = if let Some(cidr) = &cli.cidr {
+        summary.read(1);
+        DualFcidr::new(operand(cidr)?)
+    } else {
+        read_dual(input, cli.input_format, &cli.csv, cli.truncate, summary)?
     };
+    let set_operation = matches!(
+        cli.command,
+        FcidrCommand::Complement { .. }
+            | FcidrCommand::Difference { .. }
+            | FcidrCommand::Superset { .. }
+            | FcidrCommand::Union { .. }
+            | FcidrCommand::Xor { .. }
+    );
+    if (!set_operation || cli.output_dir.is_some()) && dual.v6().iter().next().is_some() {
+        return Err("IPv6 input is only supported by complement, difference, superset, union and xor, without --output-dir".into());
+    }
+    let fcidr = dual.v4();
+    let count = cli.command.count();
 
     match cli.command {
-        FcidrCommand::Complement => fcidr.complement(),
-        FcidrCommand::Difference { cidr } => fcidr.difference(cidr),
+        // Complement within the address families given, or IPv4 by default.
+        FcidrCommand::Complement { .. } if dual.v6().iter().next().is_none() => {
+            dual.v4_mut().complement();
+            &mut dual
+        }
+        FcidrCommand::Complement { .. } if fcidr.iter().next().is_none() => {
+            dual.v6_mut().complement();
+            &mut dual
+        }
+        FcidrCommand::Complement { .. } => dual.complement(),
+        FcidrCommand::Compare { operands } => {
+            let mut other = Fcidr::default();
+            for operand in &operands {
+                if Path::new(operand).is_file() {
+                    let input = BufReader::new(File::open(operand)?);
+                    other.union_set(&read(
+                        input,
+                        cli.input_format,
+                        &cli.csv,
+                        cli.truncate,
+                        summary,
+                    )?);
+                } else {
+                    summary.read(1);
+                    other.union(Token::parse(operand, cli.truncate)?);
+                }
+            }
+            let relationship = Relationship::between(fcidr, &other);
+            match cli.format {
+                Format::Text => writeln!(output, "{relationship}")?,
+                Format::Json | Format::Jsonl => writeln!(
+                    output,
+                    "{}",
+                    serde_json::json!({ "relationship": relationship })
+                )?,
+                Format::Csv => writeln!(output, "relationship\n{relationship}")?,
+            }
+            return Ok(relationship.code());
+        }
+        FcidrCommand::Difference { cidr, .. } => dual.difference(operand(&cidr)?),
+        FcidrCommand::Nearest { ip } => {
+            let nearest = fcidr
+                .nearest(ip)
+                .ok_or_else(|| fcidr::Error::NotFound("no address is covered".to_string()))?;
+            match cli.format {
+                Format::Text => writeln!(output, "{nearest}")?,
+                Format::Json | Format::Jsonl => writeln!(output, "{}", serde_json::json!(nearest))?,
+                Format::Csv => writeln!(output, "address\n{nearest}")?,
+            }
+            return Ok(exit::SUCCESS);
+        }
+        FcidrCommand::Pick {
+            size,
+            count,
+            within,
+            contiguous,
+        } => {
+            let picked = pick(fcidr, size, count, within, contiguous)?;
+            let picked = cli.order.sort(picked);
+            write(&mut output, picked, cli.format, summary)?;
+            return Ok(exit::SUCCESS);
+        }
+        FcidrCommand::Grep { .. }
+        | FcidrCommand::LintRules { .. }
+        | FcidrCommand::Redundant
+        | FcidrCommand::Run { .. } => {
+            unreachable!()
+        }
+        FcidrCommand::Supernet { .. } => {
+            dual = fcidr.supernet().map(DualFcidr::new).unwrap_or_default();
+            &mut dual
+        }
         FcidrCommand::Superset { cidr } => {
-            if fcidr.is_superset(cidr) {
-                return Ok(());
+            match operand(&cidr)? {
+                IpCidr::V4(cidr) => fcidr.require_superset(cidr)?,
+                IpCidr::V6(cidr) if !dual.is_superset(cidr) => {
+                    return Err(NotSupersetError6(cidr).into())
+                }
+                IpCidr::V6(_) => {}
             }
-            return Err(format!("not a superset of {cidr}").into());
+            return Ok(exit::SUCCESS);
         }
-        FcidrCommand::Union { cidr } => fcidr.union(cidr),
+        FcidrCommand::Union { cidr, .. } => dual.union(operand(&cidr)?),
+        FcidrCommand::Xor { cidr, .. } => dual.symmetric_difference(operand(&cidr)?),
     };
 
+    if let Some(dir) = &cli.output_dir {
+        write_shards(
+            dir,
+            dual.v4(),
+            cli.shard_by.unwrap_or(8),
+            cli.format,
+            cli.order,
+            summary,
+        )?;
+        return Ok(exit::SUCCESS);
+    }
+
+    write_output(
+        output,
+        cli.order.sort(dual.iter()),
+        count,
+        cli.format,
+        summary,
+    )?;
+    Ok(exit::SUCCESS)
+}
+
+/// Writes the CIDRs, or with `count`, only how many there are.
+fn write_output(
+    mut output: impl Write,
+    cidrs: Vec<IpCidr>,
+    count: Count,
+    format: Format,
+    summary: &mut Summary,
+) -> Result<(), Box<dyn Error>> {
+    if !count.count {
+        return write(output, cidrs, format, summary);
+    }
+    for &cidr in &cidrs {
+        summary.wrote(cidr);
+    }
+    let count = if count.addresses {
+        cidrs
+            .iter()
+            .fold(0u128, |count, cidr| count.saturating_add(size(cidr)))
+    } else {
+        cidrs.len() as u128
+    };
+    writeln!(output, "{count}")?;
+    Ok(())
+}
+
+/// Finds the dotted-quad addresses and CIDRs in the text. A CIDR with host
+/// bits set, such as the interface address 10.0.0.1/24, is truncated if
+/// `truncate` is set and skipped otherwise, as is one with an invalid prefix.
+fn extract(text: &str, truncate: bool) -> Vec<Cidr> {
+    let is_token = |c: char| c.is_ascii_digit() || c == '.' || c == '/';
+    text.split(|c: char| !is_token(c))
+        .filter_map(|token| {
+            let token = token.trim_matches(|c| c == '.' || c == '/');
+            Token::parse(token, truncate).ok()
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Relationship {
+    Equal,
+    Subset,
+    Superset,
+    Overlapping,
+    Disjoint,
+}
+
+impl Relationship {
+    fn between(fcidr: &Fcidr, other: &Fcidr) -> Self {
+        let mut only_fcidr = fcidr.clone();
+        only_fcidr.difference_set(other);
+        let mut only_other = other.clone();
+        only_other.difference_set(fcidr);
+        match (only_fcidr.get(0), only_other.get(0)) {
+            (None, None) => Self::Equal,
+            (None, Some(_)) => Self::Subset,
+            (Some(_), None) => Self::Superset,
+            _ if only_fcidr == *fcidr => Self::Disjoint,
+            _ => Self::Overlapping,
+        }
+    }
+
+    fn code(self) -> i32 {
+        match self {
+            Self::Equal => exit::SUCCESS,
+            Self::Subset => exit::SUBSET,
+            Self::Superset => exit::SUPERSET,
+            Self::Overlapping => exit::OVERLAPPING,
+            Self::Disjoint => exit::DISJOINT,
+        }
+    }
+}
+
+impl fmt::Display for Relationship {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Equal => "equal",
+            Self::Subset => "subset",
+            Self::Superset => "superset",
+            Self::Overlapping => "overlapping",
+            Self::Disjoint => "disjoint",
+        })
+    }
+}
+
+/// The IPv6 counterpart of [`fcidr::NotSupersetError`].
+#[derive(Debug)]
+struct NotSupersetError6(Cidr6);
+
+impl fmt::Display for NotSupersetError6 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a superset of {}", self.0)
+    }
+}
+
+impl Error for NotSupersetError6 {}
+
+#[derive(Debug)]
+struct RedundantError(usize);
+
+impl fmt::Display for RedundantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "found {} redundant CIDR(s)", self.0)
+    }
+}
+
+impl Error for RedundantError {}
+
+#[derive(Serialize)]
+struct Redundancy {
+    line: usize,
+    #[serde(serialize_with = "serialize_display")]
+    cidr: Cidr,
+    #[serde(serialize_with = "serialize_display")]
+    covered_by: Cidr,
+    covered_by_line: usize,
+}
+
+fn serialize_display<S: Serializer>(
+    value: &impl fmt::Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn redundant(
+    input: impl BufRead,
+    mut output: impl Write,
+    cli: &Cli,
+    summary: &mut Summary,
+) -> Result<(), Box<dyn Error>> {
+    let mut entries = Vec::new();
+    read_each(
+        input,
+        cli.input_format,
+        &cli.csv,
+        cli.truncate,
+        summary,
+        |line, cidr: Cidr| entries.push((cidr, line)),
+    )?;
+    // Sorted, any CIDR covering another comes before it, and among duplicates
+    // the first line comes first.
+    entries.sort();
+    let mut redundancies = Vec::new();
+    let mut cover: Option<(Cidr, usize)> = None;
+    for (cidr, line) in entries {
+        match cover {
+            Some((covered_by, covered_by_line)) if covered_by.contains(cidr) => {
+                redundancies.push(Redundancy {
+                    line,
+                    cidr,
+                    covered_by,
+                    covered_by_line,
+                })
+            }
+            _ => cover = Some((cidr, line)),
+        }
+    }
+    redundancies.sort_by_key(|r| r.line);
+    match cli.format {
+        Format::Text => {
+            for r in &redundancies {
+                writeln!(
+                    output,
+                    "line {}: {} is covered by {} on line {}",
+                    r.line, r.cidr, r.covered_by, r.covered_by_line
+                )?;
+            }
+        }
+        Format::Json => writeln!(output, "{}", serde_json::to_string(&redundancies)?)?,
+        Format::Jsonl => {
+            for r in &redundancies {
+                writeln!(output, "{}", serde_json::to_string(r)?)?;
+            }
+        }
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(output);
+            for r in &redundancies {
+                writer.serialize(r)?;
+            }
+            writer.flush()?;
+        }
+    }
+    if redundancies.is_empty() {
+        Ok(())
+    } else {
+        Err(RedundantError(redundancies.len()).into())
+    }
+}
+
+#[derive(Debug)]
+struct ShadowedError(usize);
+
+impl fmt::Display for ShadowedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rule(s) shadowed by earlier rules", self.0)
+    }
+}
+
+impl Error for ShadowedError {}
+
+/// Reads `allow <cidr>` and `deny <cidr>` rules along with the line on which
+/// each appears, skipping blank lines and `#` comments.
+fn read_rules(
+    input: impl BufRead,
+    truncate: bool,
+    summary: &mut Summary,
+) -> Result<(RuleSet, Vec<usize>), Box<dyn Error>> {
+    let mut rules = Vec::new();
+    let mut lines = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        let rule = line.split_once('#').map_or(line.as_str(), |(rule, _)| rule);
+        let mut tokens = rule.split_whitespace();
+        let Some(action) = tokens.next() else {
+            continue;
+        };
+        let action = action.parse::<Action>().map_err(|e| fcidr::Error::Syntax {
+            line: index + 1,
+            column: column_of(&line, action),
+            token: action.to_string(),
+            error: Box::new(e),
+        })?;
+        let token = tokens.next().unwrap_or_default();
+        rules.push((
+            action,
+            parse_at(token, truncate, index + 1, column_of(&line, token))?,
+        ));
+        lines.push(index + 1);
+        summary.read(1);
+    }
+    Ok((RuleSet::new(rules), lines))
+}
+
+fn lint_rules(
+    input: impl BufRead,
+    output: impl Write,
+    cli: &Cli,
+    summary: &mut Summary,
+) -> Result<(), Box<dyn Error>> {
+    let (rules, lines) = read_rules(input, cli.truncate, summary)?;
+    let shadowed = rules.shadowed();
+    for &index in &shadowed {
+        let (action, cidr) = rules.rules()[index];
+        let by = rules.rules()[..index]
+            .iter()
+            .zip(&lines)
+            .filter(|((_, earlier), _)| earlier.contains(cidr) || cidr.contains(*earlier))
+            .map(|(_, line)| line.to_string())
+            .collect::<Vec<_>>();
+        cli.log_format.warning(
+            "shadowed",
+            &format!(
+                "line {}: {action} {cidr} is shadowed by line(s) {}",
+                lines[index],
+                by.join(", ")
+            ),
+            Some(lines[index]),
+        );
+    }
+    write_output(
+        output,
+        cli.order.sort(rules.allowed()),
+        cli.command.count(),
+        cli.format,
+        summary,
+    )?;
+    if shadowed.is_empty() {
+        Ok(())
+    } else {
+        Err(ShadowedError(shadowed.len()).into())
+    }
+}
+
+fn parse_prefix(s: &str) -> Result<u8, String> {
+    match s.strip_prefix('/').unwrap_or(s).parse::<u8>() {
+        Ok(prefix) if prefix as u32 <= u32::BITS => Ok(prefix),
+        _ => Err("expected a prefix length from /0 to /32".to_string()),
+    }
+}
+
+fn parse_shard_prefix(s: &str) -> Result<u8, String> {
+    match parse_prefix(s) {
+        Ok(prefix) if prefix % 8 == 0 && prefix > 0 => Ok(prefix),
+        _ => Err("expected one of /8, /16, /24 or /32".to_string()),
+    }
+}
+
+/// Picks the lowest free blocks of the given prefix length within a range,
+/// where free space is any not covered by `used`.
+fn pick(
+    used: &Fcidr,
+    prefix: u8,
+    count: u64,
+    within: Cidr,
+    contiguous: bool,
+) -> Result<Vec<Cidr>, fcidr::Error> {
+    let mut free = Fcidr::new(within);
+    free.difference_set(used);
+    // Merge adjacent free blocks into inclusive address ranges.
+    let mut runs: Vec<(u64, u64)> = Vec::new();
+    for cidr in &free {
+        let (first, last) = (
+            u32::from(cidr.first()) as u64,
+            u32::from(cidr.last()) as u64,
+        );
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == first => *end = last,
+            _ => runs.push((first, last)),
+        }
+    }
+    let size = 1u64 << (u32::BITS - prefix as u32);
+    let mut picked = Vec::new();
+    for (first, last) in runs {
+        let mut network = (first + size - 1) / size * size;
+        if contiguous && network.saturating_add(count.saturating_mul(size)) - 1 > last {
+            continue;
+        }
+        while network + size - 1 <= last && (picked.len() as u64) < count {
+            picked.push(Cidr::new((network as u32).into(), prefix)?);
+            network += size;
+        }
+        if picked.len() as u64 == count {
+            return Ok(picked);
+        }
+    }
+    Err(fcidr::Error::Exhausted(if contiguous {
+        format!("no {count} contiguous free /{prefix} blocks within {within}")
+    } else {
+        format!(
+            "only {} free /{prefix} blocks within {within}, {count} requested",
+            picked.len()
+        )
+    }))
+}
+
+/// The most files `write_shards` will write, beyond which it fails up front.
+const MAX_SHARDS: u64 = 1 << 16;
+
+/// Writes the CIDRs into one file per block of the given prefix length, named
+/// after the significant octets of the block, e.g. `10.txt` for 10.0.0.0/8.
+fn write_shards(
+    dir: &Path,
+    fcidr: &Fcidr,
+    prefix: u8,
+    format: Format,
+    order: Order,
+    summary: &mut Summary,
+) -> Result<(), Box<dyn Error>> {
+    let shift = u32::BITS - prefix as u32;
+    let shard_of =
+        |cidr: Cidr| Cidr::new((u32::from(cidr.network()) >> shift << shift).into(), prefix);
+    // Count the shards first, so that too many fail before any is written.
+    let mut count = 0u64;
+    let mut last = None;
     for cidr in fcidr {
-        println!("{cidr}");
+        if cidr.prefix() >= prefix {
+            let shard = shard_of(cidr)?;
+            if last != Some(shard) {
+                count += 1;
+                last = Some(shard);
+            }
+        } else {
+            count += 1u64 << (prefix - cidr.prefix());
+            last = None;
+        }
+        if count > MAX_SHARDS {
+            return Err(fcidr::Error::LimitExceeded(format!(
+                "sharding by /{prefix} would write more than {MAX_SHARDS} files"
+            ))
+            .into());
+        }
     }
+    fs::create_dir_all(dir)?;
+    let extension = match format {
+        Format::Text => "txt",
+        Format::Json => "json",
+        Format::Jsonl => "jsonl",
+        Format::Csv => "csv",
+    };
+    let mut write_shard = |shard: Cidr, fcidr: &Fcidr| -> Result<(), Box<dyn Error>> {
+        let name = shard.network().octets()[..prefix as usize / 8]
+            .iter()
+            .map(u8::to_string)
+            .collect::<Vec<_>>()
+            .join(".");
+        let mut file = BufWriter::new(File::create(dir.join(format!("{name}.{extension}")))?);
+        write(&mut file, order.sort(fcidr), format, summary)?;
+        file.flush()?;
+        Ok(())
+    };
+    // The set iterates in address order, so each shard is complete once a
+    // block beyond it comes up.
+    let mut current: Option<(Cidr, Fcidr)> = None;
+    for cidr in fcidr {
+        if cidr.prefix() >= prefix {
+            let shard = shard_of(cidr)?;
+            match &mut current {
+                Some((network, blocks)) if *network == shard => {
+                    blocks.union(cidr);
+                }
+                _ => {
+                    if let Some((network, blocks)) = current.replace((shard, Fcidr::new(cidr))) {
+                        write_shard(network, &blocks)?;
+                    }
+                }
+            }
+        } else {
+            if let Some((network, blocks)) = current.take() {
+                write_shard(network, &blocks)?;
+            }
+            let first = u32::from(cidr.first()) as u64;
+            for i in 0..1u64 << (prefix - cidr.prefix()) {
+                let shard = Cidr::new(((first + (i << shift)) as u32).into(), prefix)?;
+                write_shard(shard, &Fcidr::new(shard))?;
+            }
+        }
+    }
+    if let Some((network, blocks)) = current {
+        write_shard(network, &blocks)?;
+    }
+    Ok(())
+}
 
+/// Wraps the input in a decoder if it starts with gzip or zstd magic bytes.
+#[cfg_attr(not(feature = "compress"), allow(unused_mut))]
+fn decompress<'a>(mut input: impl BufRead + 'a) -> io::Result<Box<dyn BufRead + 'a>> {
+    #[cfg(feature = "compress")]
+    {
+        let magic = input.fill_buf()?;
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            return Ok(Box::new(BufReader::new(
+                flate2::bufread::MultiGzDecoder::new(input),
+            )));
+        }
+        if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(input)?)));
+        }
+    }
+    Ok(Box::new(input))
+}
+
+fn read(
+    input: impl BufRead,
+    format: Format,
+    csv: &Csv,
+    truncate: bool,
+    summary: &mut Summary,
+) -> Result<Fcidr, Box<dyn Error>> {
+    let mut fcidr = Fcidr::default();
+    read_each(input, format, csv, truncate, summary, |_, cidr: Cidr| {
+        fcidr.union(cidr);
+    })?;
+    Ok(fcidr)
+}
+
+/// Like [`read`], but detecting the address family of each CIDR.
+fn read_dual(
+    input: impl BufRead,
+    format: Format,
+    csv: &Csv,
+    truncate: bool,
+    summary: &mut Summary,
+) -> Result<DualFcidr, Box<dyn Error>> {
+    let mut fcidr = DualFcidr::default();
+    read_each(input, format, csv, truncate, summary, |_, cidr: IpCidr| {
+        fcidr.union(cidr);
+    })?;
+    Ok(fcidr)
+}
+
+/// Calls `each` with every input CIDR and the line on which it appears,
+/// clearing host bits first if `truncate` is set, and tallies them in
+/// `summary`.
+fn read_each<T>(
+    input: impl BufRead,
+    format: Format,
+    csv: &Csv,
+    truncate: bool,
+    summary: &mut Summary,
+    mut each: impl FnMut(usize, T),
+) -> Result<(), Box<dyn Error>>
+where
+    T: Token,
+{
+    let mut each = |line, cidr| {
+        summary.read(1);
+        each(line, cidr)
+    };
+    let mut input = decompress(input)?;
+    match format {
+        Format::Text => {
+            for (index, line) in input.lines().enumerate() {
+                let line = line?;
+                let token = line.trim();
+                if token.is_empty() {
+                    continue;
+                }
+                each(
+                    index + 1,
+                    parse_at(token, truncate, index + 1, column_of(&line, token))?,
+                );
+            }
+        }
+        Format::Json => {
+            let mut text = String::new();
+            input.read_to_string(&mut text)?;
+            let cidrs = serde_json::from_str::<Vec<String>>(&text)?;
+            // The array is known to be well-formed, so walk its elements again
+            // only to find where each starts, letting errors point back at it.
+            let mut cursor = text.find('[').map_or(0, |i| i + 1);
+            for cidr in cidrs {
+                cursor += text[cursor..]
+                    .find(|c: char| !c.is_whitespace() && c != ',')
+                    .unwrap_or_default();
+                let mut element = serde_json::Deserializer::from_str(&text[cursor..])
+                    .into_iter::<serde::de::IgnoredAny>();
+                element.next().transpose()?;
+                let start = text[..cursor].rfind('\n').map_or(0, |i| i + 1);
+                let line = text[..cursor].matches('\n').count() + 1;
+                // Point inside the opening quote, at the token itself.
+                let column = column_of(&text[start..], &text[cursor..]) + 1;
+                cursor += element.byte_offset();
+                each(line, parse_at(&cidr, truncate, line, column)?);
+            }
+        }
+        Format::Csv => {
+            let delimiter = u8::try_from(csv.delimiter)
+                .map_err(|_| "the CSV delimiter must be an ASCII character")?;
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(delimiter)
+                .from_reader(input);
+            let headers = reader.headers()?;
+            let column = match csv.column.parse::<usize>() {
+                Ok(index) if index > 0 && index <= headers.len() => index - 1,
+                Ok(index) => {
+                    return Err(fcidr::Error::Parse(format!("no CSV column {index}")).into())
+                }
+                _ => headers
+                    .iter()
+                    .position(|header| header == csv.column)
+                    .ok_or_else(|| {
+                        fcidr::Error::Parse(format!("no CSV column named '{}'", csv.column))
+                    })?,
+            };
+            for record in reader.records() {
+                let record = record?;
+                let line = record.position().map_or(0, |p| p.line() as usize);
+                let token = record.get(column).unwrap_or_default().trim();
+                if token.is_empty() {
+                    continue;
+                }
+                each(line, parse_at(token, truncate, line, column + 1)?);
+            }
+        }
+        Format::Jsonl => {
+            for (index, line) in input.lines().enumerate() {
+                let line = line?;
+                let token = line.trim();
+                if token.is_empty() {
+                    continue;
+                }
+                let column = column_of(&line, token);
+                let cidr = serde_json::from_str::<String>(token).map_err(|e| {
+                    let message = match e.classify() {
+                        serde_json::error::Category::Data => "expected a JSON string",
+                        serde_json::error::Category::Eof => "unexpected end of JSON",
+                        _ => "invalid JSON",
+                    };
+                    fcidr::Error::Syntax {
+                        line: index + 1,
+                        column: column + e.column().saturating_sub(1),
+                        token: token.to_string(),
+                        error: Box::new(fcidr::Error::Parse(message.to_string())),
+                    }
+                })?;
+                each(index + 1, parse_at(&cidr, truncate, index + 1, column + 1)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The 1-based column at which `token`, a subslice of `line`, starts.
+fn column_of(line: &str, token: &str) -> usize {
+    let offset = token.as_ptr() as usize - line.as_ptr() as usize;
+    line[..offset].chars().count() + 1
+}
+
+/// A CIDR type read from input, where a bare address stands for a host block.
+trait Token: Sized {
+    /// Parses the token, clearing host bits rather than rejecting them if
+    /// `truncate` is set.
+    fn parse(token: &str, truncate: bool) -> Result<Self, fcidr::Error>;
+}
+
+impl Token for Cidr {
+    fn parse(token: &str, truncate: bool) -> Result<Self, fcidr::Error> {
+        if truncate {
+            Cidr::from_str_truncate(token)
+        } else {
+            Cidr::from_str_lossy(token)
+        }
+    }
+}
+
+impl Token for IpCidr {
+    fn parse(token: &str, truncate: bool) -> Result<Self, fcidr::Error> {
+        if truncate {
+            IpCidr::from_str_truncate(token)
+        } else {
+            IpCidr::from_str_lossy(token)
+        }
+    }
+}
+
+fn parse_at<T>(token: &str, truncate: bool, line: usize, column: usize) -> Result<T, fcidr::Error>
+where
+    T: Token,
+{
+    T::parse(token, truncate).map_err(|e| fcidr::Error::Syntax {
+        line,
+        column,
+        token: token.to_string(),
+        error: Box::new(e),
+    })
+}
+
+fn write(
+    mut output: impl Write,
+    cidrs: impl IntoIterator<Item = impl Into<IpCidr>>,
+    format: Format,
+    summary: &mut Summary,
+) -> Result<(), Box<dyn Error>> {
+    let cidrs = cidrs
+        .into_iter()
+        .map(Into::into)
+        .inspect(|&cidr| summary.wrote(cidr));
+    match format {
+        Format::Text => {
+            for cidr in cidrs {
+                writeln!(output, "{cidr}")?;
+            }
+        }
+        Format::Json => {
+            let cidrs = cidrs.into_iter().map(|c| c.to_string()).collect::<Vec<_>>();
+            writeln!(output, "{}", serde_json::to_string(&cidrs)?)?;
+        }
+        Format::Csv => {
+            writeln!(output, "cidr")?;
+            for cidr in cidrs {
+                writeln!(output, "{cidr}")?;
+            }
+        }
+        Format::Jsonl => {
+            for cidr in cidrs {
+                writeln!(output, "{}", serde_json::to_string(&cidr.to_string())?)?;
+            }
+        }
+    }
     Ok(())
 }
+
+/// A declarative chain of set operations, for example:
+///
+/// ```yaml
+/// sources:
+///   - file: allow.txt
+///   - url: https://example.com/ranges.json
+///     format: json
+/// operations:
+///   - difference:
+///       cidrs: [10.0.64.0/20]
+///   - intersect:
+///       file: universe.txt
+/// sinks:
+///   - file: out.jsonl
+///     format: jsonl
+/// ```
+///
+/// The sources are unioned together before the operations are applied in
+/// order. Without any sinks, the result is written as text to stdout. Relative
+/// file paths are resolved against the directory containing the pipeline.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Pipeline {
+    #[serde(skip)]
+    root: PathBuf,
+    #[serde(default)]
+    sources: Vec<Source>,
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    operations: Vec<Operation>,
+    #[serde(default)]
+    sinks: Vec<Sink>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Source {
+    file: Option<PathBuf>,
+    url: Option<String>,
+    cidrs: Option<Vec<String>>,
+    #[serde(default)]
+    format: Format,
+    #[serde(default)]
+    csv: Csv,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Operation {
+    Complement,
+    Difference(Source),
+    Intersect(Source),
+    Union(Source),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Sink {
+    file: Option<PathBuf>,
+    #[serde(default)]
+    format: Format,
+}
+
+impl Pipeline {
+    fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut pipeline: Self = serde_yaml::from_reader(File::open(path)?)?;
+        pipeline.root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(pipeline)
+    }
+
+    fn load_source(
+        &self,
+        source: &Source,
+        truncate: bool,
+        summary: &mut Summary,
+    ) -> Result<Fcidr, Box<dyn Error>> {
+        match source {
+            Source {
+                file: Some(file),
+                url: None,
+                cidrs: None,
+                format,
+                csv,
+            } => read(
+                BufReader::new(File::open(self.root.join(file))?),
+                *format,
+                csv,
+                truncate,
+                summary,
+            ),
+            Source {
+                file: None,
+                url: Some(url),
+                cidrs: None,
+                format,
+                csv,
+            } => read(
+                BufReader::new(ureq::get(url).call()?.into_reader()),
+                *format,
+                csv,
+                truncate,
+                summary,
+            ),
+            Source {
+                file: None,
+                url: None,
+                cidrs: Some(cidrs),
+                ..
+            } => cidrs.iter().try_fold(Fcidr::default(), |mut fcidr, cidr| {
+                fcidr.union(Token::parse(cidr, truncate)?);
+                Ok(fcidr)
+            }),
+            _ => Err("a source must have exactly one of `file`, `url` or `cidrs`".into()),
+        }
+    }
+
+    /// Unions the sources and applies the operations to them.
+    fn evaluate(&self, truncate: bool, summary: &mut Summary) -> Result<Fcidr, Box<dyn Error>> {
+        let mut fcidr = Fcidr::default();
+        for source in &self.sources {
+            for cidr in &self.load_source(source, truncate, summary)? {
+                fcidr.union(cidr);
+            }
+        }
+        for operation in &self.operations {
+            match operation {
+                Operation::Complement => {
+                    fcidr.complement();
+                }
+                Operation::Difference(source) => {
+                    for cidr in &self.load_source(source, truncate, summary)? {
+                        fcidr.difference(cidr);
+                    }
+                }
+                Operation::Intersect(source) => {
+                    for cidr in self
+                        .load_source(source, truncate, summary)?
+                        .complement()
+                        .iter()
+                    {
+                        fcidr.difference(cidr);
+                    }
+                }
+                Operation::Union(source) => {
+                    for cidr in &self.load_source(source, truncate, summary)? {
+                        fcidr.union(cidr);
+                    }
+                }
+            }
+        }
+        Ok(fcidr)
+    }
+
+    fn run(&self, truncate: bool, summary: &mut Summary) -> Result<(), Box<dyn Error>> {
+        let fcidr = self.evaluate(truncate, summary)?;
+        if self.sinks.is_empty() {
+            return write(stdout().lock(), &fcidr, Format::Text, summary);
+        }
+        for sink in &self.sinks {
+            match &sink.file {
+                Some(file) => {
+                    let mut file = BufWriter::new(File::create(self.root.join(file))?);
+                    write(&mut file, &fcidr, sink.format, summary)?;
+                    file.flush()?;
+                }
+                None => write(stdout().lock(), &fcidr, sink.format, summary)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the command line on the input, returning what it writes.
+    fn fcidr(args: &[&str], input: &str) -> Result<String, Box<dyn Error>> {
+        let cli = Cli::try_parse_from(["fcidr"].iter().chain(args))?;
+        let mut output = Vec::new();
+        run(cli, input.as_bytes(), &mut output, &mut Summary::default())?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn cli() {
+        Cli::command().debug_assert();
+        let cli = Cli::try_parse_from(["fcidr", "pick", "--size", "/24", "--count", "2"]).unwrap();
+        assert!(matches!(cli.command, FcidrCommand::Pick { count: 2, .. }));
+        assert!(Cli::try_parse_from(["fcidr", "pick", "--size", "/24", "-c"]).is_err());
+        let picked = fcidr(
+            &[
+                "pick",
+                "--size",
+                "/24",
+                "--count",
+                "16",
+                "--within",
+                "10.0.0.0/16",
+            ],
+            "10.0.0.0/24\n",
+        )
+        .unwrap();
+        assert_eq!(picked.lines().count(), 16);
+        assert!(picked.starts_with("10.0.1.0/24\n"));
+        assert!(picked.ends_with("10.0.16.0/24\n"));
+        assert!(Cli::try_parse_from(["fcidr", "pick", "--size", "/24", "--count", "0"]).is_err());
+        assert!(Cli::try_parse_from([
+            "fcidr",
+            "pick",
+            "--size",
+            "/24",
+            "--count",
+            "0",
+            "--contiguous"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn cli_count() {
+        let count = |args: &[&str]| {
+            Cli::try_parse_from(["fcidr"].iter().chain(args))
+                .map(|cli| (cli.command.count().count, cli.command.count().addresses))
+        };
+        assert_eq!(count(&["union", "10.0.0.0/8"]).unwrap(), (false, false));
+        assert_eq!(
+            count(&["union", "10.0.0.0/8", "-c"]).unwrap(),
+            (true, false)
+        );
+        assert_eq!(
+            count(&["complement", "-c", "--addresses"]).unwrap(),
+            (true, true)
+        );
+        assert_eq!(count(&["grep", "--count"]).unwrap(), (true, false));
+        assert!(count(&["-c", "union", "10.0.0.0/8"]).is_err());
+        assert!(count(&["union", "10.0.0.0/8", "--addresses"]).is_err());
+        assert!(count(&["union", "10.0.0.0/8", "-c", "--output-dir", "out"]).is_err());
+        assert_eq!(
+            fcidr(&["union", "10.0.0.0/8", "-c"], "11.0.0.0/8\n").unwrap(),
+            "1\n"
+        );
+        assert_eq!(
+            fcidr(&["supernet", "-c", "--addresses"], "10.0.0.0/8\n").unwrap(),
+            "16777216\n"
+        );
+    }
+
+    #[test]
+    fn read_json() {
+        let text = "[\n  \"10.0.0.0/8\",\n  \"\\u0031\\u0030.1.0.0/16\",  \"10.0.0.1/33\"\n]";
+        let mut cidrs = Vec::new();
+        let error = read_each(
+            text.as_bytes(),
+            Format::Json,
+            &Csv::default(),
+            false,
+            &mut Summary::default(),
+            |line, cidr: Cidr| cidrs.push((line, cidr)),
+        )
+        .unwrap_err();
+        assert_eq!(
+            cidrs,
+            [
+                (2, "10.0.0.0/8".parse().unwrap()),
+                (3, "10.1.0.0/16".parse().unwrap())
+            ]
+        );
+        assert_eq!(
+            error.to_string(),
+            "line 3, column 30: network prefix '33' must be 32 or less in '10.0.0.1/33'"
+        );
+    }
+
+    #[test]
+    fn shards() {
+        let dir = std::env::temp_dir().join(format!("fcidr-shards-{}", std::process::id()));
+        let fcidr = ["10.0.0.0/24", "10.1.0.0/16", "172.16.0.0/12", "12.0.0.0/7"]
+            .into_iter()
+            .map(|cidr| cidr.parse::<Cidr>().unwrap())
+            .collect::<Fcidr>();
+        let order = Order {
+            sort_by: SortBy::Address,
+            reverse: false,
+        };
+        write_shards(
+            &dir,
+            &fcidr,
+            8,
+            Format::Text,
+            order,
+            &mut Summary::default(),
+        )
+        .unwrap();
+        let mut names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["10.txt", "12.txt", "13.txt", "172.txt"]);
+        assert_eq!(
+            fs::read_to_string(dir.join("10.txt")).unwrap(),
+            "10.0.0.0/24\n10.1.0.0/16\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("13.txt")).unwrap(),
+            "13.0.0.0/8\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("172.txt")).unwrap(),
+            "172.16.0.0/12\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        write_shards(
+            &dir,
+            &fcidr,
+            16,
+            Format::Text,
+            order,
+            &mut Summary::default(),
+        )
+        .unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2 + 2 * 256 + 16);
+        assert_eq!(
+            fs::read_to_string(dir.join("172.16.txt")).unwrap(),
+            "172.16.0.0/16\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("10.1.txt")).unwrap(),
+            "10.1.0.0/16\n"
+        );
+        assert!(!dir.join("172.32.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let all = Fcidr::new(Cidr::default());
+        assert!(matches!(
+            write_shards(&dir, &all, 24, Format::Text, order, &mut Summary::default())
+                .unwrap_err()
+                .downcast_ref::<fcidr::Error>(),
+            Some(fcidr::Error::LimitExceeded(_))
+        ));
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn truncate() {
+        let read = |args: &[&str]| {
+            let cli = Cli::try_parse_from(args).unwrap();
+            read_dual(
+                "10.1.2.3/24\n".as_bytes(),
+                cli.input_format,
+                &cli.csv,
+                cli.truncate,
+                &mut Summary::default(),
+            )
+        };
+        assert_eq!(
+            read(&["fcidr", "--truncate", "complement"])
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            [IpCidr::V4("10.1.2.0/24".parse().unwrap())]
+        );
+        assert!(read(&["fcidr", "complement"])
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 1, column 1: "));
+    }
+
+    #[test]
+    fn summary() {
+        let mut summary = Summary::default();
+        let dual = read_dual(
+            "10.0.0.0/25\n10.0.0.128/25\n::/127\n".as_bytes(),
+            Format::Text,
+            &Csv::default(),
+            false,
+            &mut summary,
+        )
+        .unwrap();
+        let mut output = Vec::new();
+        write(&mut output, dual.iter(), Format::Text, &mut summary).unwrap();
+        assert_eq!(output, b"10.0.0.0/24\n::/127\n");
+        assert_eq!(
+            LogFormat::Text.footer(&summary, Duration::from_millis(5)),
+            "3 block(s) in, 2 block(s) out, 258 address(es) covered in 5.00ms"
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(
+                &LogFormat::Json.footer(&summary, Duration::from_millis(5))
+            )
+            .unwrap(),
+            serde_json::json!({
+                "level": "info",
+                "kind": "summary",
+                "blocks_in": 3,
+                "blocks_out": 2,
+                "addresses": 258,
+                "elapsed_ms": 5.0,
+            })
+        );
+    }
+
+    #[test]
+    fn log_format() {
+        let error = read(
+            "10.0.0.0/8\n 192.168.0.0/33\n".as_bytes(),
+            Format::Text,
+            &Csv::default(),
+            false,
+            &mut Summary::default(),
+        )
+        .unwrap_err();
+        let (kind, code, line) = classify(error.as_ref());
+        assert_eq!((kind, code, line), ("parse", exit::DATA, Some(2)));
+        assert_eq!(
+            LogFormat::Text.error_entry(kind, error.as_ref(), line),
+            "Error: line 2, column 2: network prefix '33' must be 32 or less in '192.168.0.0/33'"
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&LogFormat::Json.error_entry(
+                kind,
+                error.as_ref(),
+                line
+            ))
+            .unwrap(),
+            serde_json::json!({
+                "level": "error",
+                "kind": "parse",
+                "message": "network prefix '33' must be 32 or less",
+                "line": 2,
+                "column": 2,
+                "token": "192.168.0.0/33",
+            })
+        );
+        let recover = |args: &[&str]| LogFormat::recover(args);
+        assert!(matches!(
+            recover(&["fcidr", "--log-format=json", "--nope", "union"]),
+            LogFormat::Json
+        ));
+        assert!(matches!(
+            recover(&["fcidr", "complement", "--errors", "json", "--bogus"]),
+            LogFormat::Json
+        ));
+        assert!(matches!(
+            recover(&["fcidr", "--log-format", "yaml", "complement"]),
+            LogFormat::Text
+        ));
+        assert!(matches!(
+            recover(&["fcidr", "complement", "--", "--log-format=json", "extra"]),
+            LogFormat::Text
+        ));
+        let error = fcidr::Error::Exhausted("no free /24 left".to_string());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&LogFormat::Json.error_entry(
+                "exhausted",
+                &error,
+                None
+            ))
+            .unwrap(),
+            serde_json::json!({
+                "level": "error",
+                "kind": "exhausted",
+                "message": "no free /24 left",
+                "line": null,
+            })
+        );
+    }
+
+    #[test]
+    fn redundant() {
+        let cli = Cli::try_parse_from(["fcidr", "redundant"]).unwrap();
+        let input = "10.1.0.0/16\n10.0.0.0/8\n\n192.168.0.0/24\n10.1.2.0/24\n192.168.0.0/24\n";
+        let mut output = Vec::new();
+        let error = super::redundant(input.as_bytes(), &mut output, &cli, &mut Summary::default())
+            .unwrap_err();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "line 1: 10.1.0.0/16 is covered by 10.0.0.0/8 on line 2\n\
+             line 5: 10.1.2.0/24 is covered by 10.0.0.0/8 on line 2\n\
+             line 6: 192.168.0.0/24 is covered by 192.168.0.0/24 on line 4\n"
+        );
+        assert_eq!(error.to_string(), "found 3 redundant CIDR(s)");
+
+        let cli = Cli::try_parse_from(["fcidr", "redundant", "--format", "jsonl"]).unwrap();
+        let mut output = Vec::new();
+        super::redundant(
+            "10.0.0.0/9\n10.128.0.0/9\n".as_bytes(),
+            &mut output,
+            &cli,
+            &mut Summary::default(),
+        )
+        .unwrap();
+        assert!(output.is_empty());
+        let mut output = Vec::new();
+        super::redundant(
+            "10.0.0.0/8\n10.0.0.1\n".as_bytes(),
+            &mut output,
+            &cli,
+            &mut Summary::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"line\":2,\"cidr\":\"10.0.0.1/32\",\"covered_by\":\"10.0.0.0/8\",\"covered_by_line\":1}\n"
+        );
+    }
+
+    #[test]
+    fn relationship() {
+        let set = |cidrs: &[&str]| {
+            cidrs
+                .iter()
+                .map(|cidr| cidr.parse::<Cidr>().unwrap())
+                .collect::<Fcidr>()
+        };
+        for (fcidr, other, relationship, code) in [
+            (
+                &["10.0.0.0/9", "10.128.0.0/9"][..],
+                &["10.0.0.0/8"][..],
+                Relationship::Equal,
+                exit::SUCCESS,
+            ),
+            (
+                &["10.1.0.0/16"],
+                &["10.0.0.0/8"],
+                Relationship::Subset,
+                exit::SUBSET,
+            ),
+            (
+                &["10.0.0.0/8", "192.168.0.0/16"],
+                &["10.1.0.0/16", "192.168.0.0/24"],
+                Relationship::Superset,
+                exit::SUPERSET,
+            ),
+            (
+                &["10.0.0.0/8"],
+                &["10.1.0.0/16", "11.0.0.0/8"],
+                Relationship::Overlapping,
+                exit::OVERLAPPING,
+            ),
+            (
+                &["10.0.0.0/8"],
+                &["11.0.0.0/8"],
+                Relationship::Disjoint,
+                exit::DISJOINT,
+            ),
+        ] {
+            let between = Relationship::between(&set(fcidr), &set(other));
+            assert_eq!(between, relationship, "{fcidr:?} {other:?}");
+            assert_eq!(between.code(), code);
+        }
+        assert_eq!(
+            Relationship::between(&set(&["11.0.0.0/8"]), &set(&["10.0.0.0/8"])).to_string(),
+            "disjoint"
+        );
+    }
+
+    #[test]
+    fn extract() {
+        let cidrs = |text: &str, truncate: bool| {
+            super::extract(text, truncate)
+                .iter()
+                .map(Cidr::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            cidrs(
+                "Oct 16 03:41:02 sshd[812]: Failed password from 203.0.113.7 port 22, route 10.0.0.0/8 via 192.168.1.1.",
+                false
+            ),
+            ["203.0.113.7/32", "10.0.0.0/8", "192.168.1.1/32"]
+        );
+        assert_eq!(
+            cidrs("<td>10.0.0.128/25</td><td>10.0.0.0/25.</td>", false),
+            ["10.0.0.128/25", "10.0.0.0/25"]
+        );
+        assert!(cidrs("fcidr 1.2.3.4.5 built with rustc 1.70.0", false).is_empty());
+        assert!(cidrs("inet 10.1.2.3/24 brd 10.1.2.255/33", false).is_empty());
+        assert_eq!(
+            cidrs("inet 10.1.2.3/24 brd 10.1.2.255/33", true),
+            ["10.1.2.0/24"]
+        );
+    }
+
+    #[test]
+    fn pick() {
+        let used = ["10.0.0.0/24", "10.0.2.0/24", "10.0.4.128/25"]
+            .into_iter()
+            .map(|cidr| cidr.parse::<Cidr>().unwrap())
+            .collect::<Fcidr>();
+        let within = "10.0.0.0/16".parse().unwrap();
+        let picked = |prefix, count, within, contiguous| {
+            super::pick(&used, prefix, count, within, contiguous)
+                .map(|picked| picked.iter().map(Cidr::to_string).collect::<Vec<_>>())
+        };
+        assert_eq!(
+            picked(24, 3, within, false).unwrap(),
+            ["10.0.1.0/24", "10.0.3.0/24", "10.0.5.0/24"]
+        );
+        assert_eq!(
+            picked(24, 3, within, true).unwrap(),
+            ["10.0.5.0/24", "10.0.6.0/24", "10.0.7.0/24"]
+        );
+        assert_eq!(
+            picked(25, 2, within, false).unwrap(),
+            ["10.0.1.0/25", "10.0.1.128/25"]
+        );
+        assert_eq!(
+            picked(25, 3, "10.0.4.0/23".parse().unwrap(), false).unwrap(),
+            ["10.0.4.0/25", "10.0.5.0/25", "10.0.5.128/25"]
+        );
+        assert_eq!(
+            picked(24, 3, "10.0.0.0/22".parse().unwrap(), false).unwrap_err(),
+            fcidr::Error::Exhausted(
+                "only 2 free /24 blocks within 10.0.0.0/22, 3 requested".to_string()
+            )
+        );
+        assert_eq!(
+            picked(24, 2, "10.0.0.0/22".parse().unwrap(), true).unwrap_err(),
+            fcidr::Error::Exhausted(
+                "no 2 contiguous free /24 blocks within 10.0.0.0/22".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn pipeline() {
+        let pipeline: Pipeline = serde_yaml::from_str(
+            "
+sources:
+  - cidrs: [10.0.0.0/8, 192.168.0.0/16]
+operations:
+  - difference:
+      cidrs: [10.0.0.0/9]
+  - union:
+      cidrs: [172.16.0.0/12]
+  - intersect:
+      cidrs: [10.0.0.0/8, 172.16.0.0/16]
+",
+        )
+        .unwrap();
+        assert_eq!(
+            pipeline
+                .evaluate(false, &mut Summary::default())
+                .unwrap()
+                .to_vec(),
+            [
+                "10.128.0.0/9".parse().unwrap(),
+                "172.16.0.0/16".parse().unwrap()
+            ]
+        );
+
+        let dir = std::env::temp_dir().join(format!("fcidr-pipeline-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("allow.txt"), "10.0.0.0/24\n10.0.1.0/24\n").unwrap();
+        fs::write(
+            dir.join("pipeline.yaml"),
+            "
+sources:
+  - file: allow.txt
+operations:
+  - complement
+  - intersect:
+      cidrs: [10.0.0.0/22]
+sinks:
+  - file: out.jsonl
+    format: jsonl
+",
+        )
+        .unwrap();
+        Pipeline::load(&dir.join("pipeline.yaml"))
+            .unwrap()
+            .run(false, &mut Summary::default())
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("out.jsonl")).unwrap(),
+            "\"10.0.2.0/23\"\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(serde_yaml::from_str::<Pipeline>(
+            "operations:\n  - symmetric_difference:\n      cidrs: [10.0.0.0/8]\n"
+        )
+        .is_err());
+        assert!(serde_yaml::from_str::<Pipeline>("sources:\n  - path: allow.txt\n").is_err());
+        let pipeline: Pipeline =
+            serde_yaml::from_str("sources:\n  - file: allow.txt\n    cidrs: [10.0.0.0/8]\n")
+                .unwrap();
+        assert_eq!(
+            pipeline
+                .evaluate(false, &mut Summary::default())
+                .unwrap_err()
+                .to_string(),
+            "a source must have exactly one of `file`, `url` or `cidrs`"
+        );
+    }
+
+    #[test]
+    fn jsonl() {
+        let fcidr = ["10.0.0.0/8", "192.168.0.0/24"]
+            .into_iter()
+            .map(|cidr| cidr.parse::<Cidr>().unwrap())
+            .collect::<Fcidr>();
+        let mut output = Vec::new();
+        write(&mut output, &fcidr, Format::Jsonl, &mut Summary::default()).unwrap();
+        assert_eq!(output, b"\"10.0.0.0/8\"\n\"192.168.0.0/24\"\n");
+        let read = |input: &[u8]| {
+            super::read(
+                input,
+                Format::Jsonl,
+                &Csv::default(),
+                false,
+                &mut Summary::default(),
+            )
+        };
+        assert_eq!(read(&output).unwrap(), fcidr);
+        assert_eq!(
+            read(b"\"10.0.0.0/8\"\n\n  \"10.0.0.1/33\"\n")
+                .unwrap_err()
+                .to_string(),
+            "line 3, column 4: network prefix '33' must be 32 or less in '10.0.0.1/33'"
+        );
+        assert_eq!(
+            read(b"\"10.0.0.0/8\"\n[\"10.1.0.0/16\"]\n")
+                .unwrap_err()
+                .to_string(),
+            "line 2, column 1: expected a JSON string in '[\"10.1.0.0/16\"]'"
+        );
+        assert_eq!(
+            read(b"\"10.0.0.0/8\"\n \"10.1.0.0/16\n")
+                .unwrap_err()
+                .to_string(),
+            "line 2, column 13: unexpected end of JSON in '\"10.1.0.0/16'"
+        );
+        assert_eq!(
+            read(b"10.0.0.0/8\n").unwrap_err().to_string(),
+            "line 1, column 4: expected a JSON string in '10.0.0.0/8'"
+        );
+        assert_eq!(
+            read(b"'10.0.0.0/8'\n").unwrap_err().to_string(),
+            "line 1, column 1: invalid JSON in ''10.0.0.0/8''"
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let input = "name;network;owner\nweb;10.0.0.0/24;ops\ndb; 10.0.1.0/24 ;dba\nspare;;\n";
+        let read = |args: &[&str]| {
+            let cli = Cli::try_parse_from(args).unwrap();
+            let mut cidrs = Vec::new();
+            read_each(
+                input.as_bytes(),
+                cli.input_format,
+                &cli.csv,
+                cli.truncate,
+                &mut Summary::default(),
+                |line, cidr: Cidr| cidrs.push((line, cidr.to_string())),
+            )
+            .map(|()| cidrs)
+        };
+        let expected = [
+            (2, "10.0.0.0/24".to_string()),
+            (3, "10.0.1.0/24".to_string()),
+        ];
+        let by_name = [
+            "fcidr",
+            "complement",
+            "--input-format",
+            "csv",
+            "--delimiter",
+            ";",
+            "--column",
+            "network",
+        ];
+        assert_eq!(read(&by_name).unwrap(), expected);
+        let by_index = [
+            "fcidr",
+            "complement",
+            "--input-format",
+            "csv",
+            "--delimiter",
+            ";",
+            "--column",
+            "2",
+        ];
+        assert_eq!(read(&by_index).unwrap(), expected);
+        assert_eq!(
+            read(&[
+                "fcidr",
+                "complement",
+                "--input-format",
+                "csv",
+                "--delimiter",
+                ";",
+                "--column",
+                "cidr",
+            ])
+            .unwrap_err()
+            .to_string(),
+            "no CSV column named 'cidr'"
+        );
+        for column in ["0", "4"] {
+            let error = read(&[
+                "fcidr",
+                "complement",
+                "--input-format",
+                "csv",
+                "--delimiter",
+                ";",
+                "--column",
+                column,
+            ])
+            .unwrap_err();
+            assert_eq!(error.to_string(), format!("no CSV column {column}"));
+            assert_eq!(classify(error.as_ref()), ("parse", exit::DATA, None));
+        }
+        assert_eq!(
+            read(&[
+                "fcidr",
+                "complement",
+                "--input-format",
+                "csv",
+                "--delimiter",
+                ";",
+            ])
+            .unwrap_err()
+            .to_string(),
+            "line 2, column 1: missing network prefix delimiter in 'web'"
+        );
+    }
+
+    #[test]
+    fn decompress() {
+        let text = "10.0.0.0/8\n192.168.0.0/16\n";
+        let read = |input: &[u8]| {
+            let mut text = String::new();
+            super::decompress(input)
+                .unwrap()
+                .read_to_string(&mut text)
+                .unwrap();
+            text
+        };
+        assert_eq!(read(text.as_bytes()), text);
+        #[cfg(feature = "compress")]
+        {
+            let mut gzip =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            gzip.write_all(text.as_bytes()).unwrap();
+            let gzip = gzip.finish().unwrap();
+            assert_eq!(read(&gzip), text);
+            let zstd = zstd::encode_all(text.as_bytes(), 0).unwrap();
+            assert_eq!(read(&zstd), text);
+            assert_eq!(
+                super::read(
+                    &gzip[..],
+                    Format::Text,
+                    &Csv::default(),
+                    false,
+                    &mut Summary::default()
+                )
+                .unwrap()
+                .to_vec(),
+                [
+                    "10.0.0.0/8".parse().unwrap(),
+                    "192.168.0.0/16".parse().unwrap()
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn sort() {
+        let cidrs = [
+            "10.0.0.0/24",
+            "10.0.1.0/25",
+            "10.1.0.0/16",
+            "::/120",
+            "10.2.0.0/24",
+        ]
+        .map(|cidr| IpCidr::from_str_lossy(cidr).unwrap());
+        let sorted = |sort_by, reverse| {
+            Order { sort_by, reverse }
+                .sort(cidrs)
+                .iter()
+                .map(IpCidr::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sorted(SortBy::Address, false),
+            [
+                "10.0.0.0/24",
+                "10.0.1.0/25",
+                "10.1.0.0/16",
+                "10.2.0.0/24",
+                "::/120"
+            ]
+        );
+        assert_eq!(
+            sorted(SortBy::Address, true),
+            [
+                "::/120",
+                "10.2.0.0/24",
+                "10.1.0.0/16",
+                "10.0.1.0/25",
+                "10.0.0.0/24"
+            ]
+        );
+        assert_eq!(
+            sorted(SortBy::Prefix, false),
+            [
+                "10.1.0.0/16",
+                "10.0.0.0/24",
+                "10.2.0.0/24",
+                "10.0.1.0/25",
+                "::/120"
+            ]
+        );
+        assert_eq!(
+            sorted(SortBy::Size, false),
+            [
+                "10.0.1.0/25",
+                "10.0.0.0/24",
+                "::/120",
+                "10.2.0.0/24",
+                "10.1.0.0/16"
+            ]
+        );
+        assert_eq!(
+            sorted(SortBy::Size, true),
+            [
+                "10.1.0.0/16",
+                "10.0.0.0/24",
+                "::/120",
+                "10.2.0.0/24",
+                "10.0.1.0/25"
+            ]
+        );
+    }
+
+    #[test]
+    fn ipv6() {
+        assert_eq!(
+            fcidr(&["2001:0db8:0000::1", "union", "2001:db8:8000::/33"], "").unwrap(),
+            "2001:db8::1/128\n2001:db8:8000::/33\n"
+        );
+        let dual = read_dual(
+            "10.0.0.0/8\n2001:0DB8:0000:0000::/33\n2001:db8:8000::/33\n192.168.0.1\n::ffff:0:0/96\n"
+                .as_bytes(),
+            Format::Text,
+            &Csv::default(),
+            false,
+            &mut Summary::default(),
+        )
+        .unwrap();
+        let mut output = Vec::new();
+        write(
+            &mut output,
+            dual.iter(),
+            Format::Text,
+            &mut Summary::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "10.0.0.0/8\n192.168.0.1/32\n::ffff:0.0.0.0/96\n2001:db8::/32\n"
+        );
+        assert!(fcidr(&["::/0", "pick", "--size", "/24"], "")
+            .unwrap_err()
+            .to_string()
+            .starts_with("IPv6 input is only supported by"));
+    }
+
+    #[test]
+    fn truncate_operands() {
+        assert!(fcidr(&["10.1.2.3/24", "complement"], "").is_err());
+        assert_eq!(
+            fcidr(&["--truncate", "10.1.2.3/24", "complement"], "").unwrap(),
+            fcidr(&["10.1.2.0/24", "complement"], "").unwrap()
+        );
+        assert_eq!(
+            fcidr(&["--truncate", "10.1.2.3/24", "supernet"], "").unwrap(),
+            "10.1.2.0/24\n"
+        );
+        assert!(fcidr(&["union", "10.1.2.3/24"], "10.0.0.0/8\n").is_err());
+        assert_eq!(
+            fcidr(&["--truncate", "union", "10.1.2.3/24"], "10.0.0.0/8\n").unwrap(),
+            "10.0.0.0/8\n"
+        );
+        assert_eq!(
+            fcidr(
+                &["--truncate", "difference", "10.1.2.3/24"],
+                "10.1.2.0/23\n"
+            )
+            .unwrap(),
+            "10.1.3.0/24\n"
+        );
+    }
+}
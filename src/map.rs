@@ -0,0 +1,285 @@
+use std::collections::{btree_map, BTreeMap};
+
+use crate::{trie::Node, Cidr};
+
+/// A map from CIDRs to values supporting longest-prefix-match lookups. The
+/// trie records, for each part of the address space, the keys covering it from
+/// least to most specific, while the values are held once per key.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FcidrMap<V> {
+    keys: Node<Vec<Cidr>>,
+    values: BTreeMap<Cidr, V>,
+}
+
+impl<V> FcidrMap<V> {
+    pub fn new() -> Self {
+        Self {
+            keys: Node::default(),
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn insert(&mut self, cidr: Cidr, value: V) -> Option<V> {
+        let previous = self.values.insert(cidr, value);
+        if previous.is_none() {
+            self.keys.update(cidr, &mut |keys| {
+                let i = keys.partition_point(|key| key.prefix() < cidr.prefix());
+                keys.insert(i, cidr);
+            });
+        }
+        previous
+    }
+
+    pub fn remove(&mut self, cidr: Cidr) -> Option<V> {
+        let value = self.values.remove(&cidr)?;
+        self.keys
+            .update(cidr, &mut |keys| keys.retain(|&key| key != cidr));
+        Some(value)
+    }
+
+    pub fn get(&self, cidr: Cidr) -> Option<&V> {
+        self.values.get(&cidr)
+    }
+
+    pub fn get_mut(&mut self, cidr: Cidr) -> Option<&mut V> {
+        self.values.get_mut(&cidr)
+    }
+
+    pub fn contains_key(&self, cidr: Cidr) -> bool {
+        self.values.contains_key(&cidr)
+    }
+
+    pub fn lookup<T>(&self, net: T) -> Option<(&Cidr, &V)>
+    where
+        T: Into<Cidr>,
+    {
+        let cidr = net.into();
+        // Every key containing the block covers its network address.
+        let keys = self.keys.get(cidr.network().into())?;
+        let key = keys.iter().rev().find(|key| key.contains(cidr))?;
+        self.values.get_key_value(key)
+    }
+
+    pub fn iter(&self) -> FcidrMapIter<'_, V> {
+        FcidrMapIter {
+            values: self.values.iter(),
+        }
+    }
+}
+
+impl<V> Default for FcidrMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Extend<(Cidr, V)> for FcidrMap<V> {
+    fn extend<T: IntoIterator<Item = (Cidr, V)>>(&mut self, iter: T) {
+        for (cidr, value) in iter {
+            self.insert(cidr, value);
+        }
+    }
+}
+
+impl<V> FromIterator<(Cidr, V)> for FcidrMap<V> {
+    fn from_iter<T: IntoIterator<Item = (Cidr, V)>>(iter: T) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, V> IntoIterator for &'a FcidrMap<V> {
+    type Item = (&'a Cidr, &'a V);
+
+    type IntoIter = FcidrMapIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterates over the entries of an [`FcidrMap`], each block before the blocks
+/// within it.
+#[derive(Debug)]
+pub struct FcidrMapIter<'a, V> {
+    values: btree_map::Iter<'a, Cidr, V>,
+}
+
+impl<'a, V> Iterator for FcidrMapIter<'a, V> {
+    type Item = (&'a Cidr, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.values.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut map = FcidrMap::new();
+        assert_eq!(map.insert("0.0.0.0/0".parse().unwrap(), "default"), None);
+        assert_eq!(map.insert("10.0.0.0/8".parse().unwrap(), "private"), None);
+        assert_eq!(map.insert("10.1.0.0/16".parse().unwrap(), "office"), None);
+        assert_eq!(
+            map.insert("10.1.0.0/16".parse().unwrap(), "branch"),
+            Some("office")
+        );
+        assert_eq!(map.len(), 3);
+        let lookup = |ip: &str| {
+            map.lookup(ip.parse::<Ipv4Addr>().unwrap())
+                .map(|(c, v)| (c.to_string(), *v))
+        };
+        assert_eq!(
+            lookup("10.1.2.3"),
+            Some(("10.1.0.0/16".to_string(), "branch"))
+        );
+        assert_eq!(
+            lookup("10.2.2.3"),
+            Some(("10.0.0.0/8".to_string(), "private"))
+        );
+        assert_eq!(
+            lookup("11.0.0.0"),
+            Some(("0.0.0.0/0".to_string(), "default"))
+        );
+        assert_eq!(
+            map.iter()
+                .map(|(c, v)| (c.to_string(), *v))
+                .collect::<Vec<_>>(),
+            vec![
+                ("0.0.0.0/0".to_string(), "default"),
+                ("10.0.0.0/8".to_string(), "private"),
+                ("10.1.0.0/16".to_string(), "branch"),
+            ]
+        );
+        assert_eq!(map.remove("10.0.0.0/8".parse().unwrap()), Some("private"));
+        assert_eq!(map.remove("10.0.0.0/8".parse().unwrap()), None);
+        assert_eq!(
+            map.lookup("10.2.2.3".parse::<Ipv4Addr>().unwrap())
+                .map(|(_, v)| *v),
+            Some("default")
+        );
+        assert_eq!(map.remove("0.0.0.0/0".parse().unwrap()), Some("default"));
+        assert_eq!(map.lookup("10.2.2.3".parse::<Ipv4Addr>().unwrap()), None);
+        assert_eq!(map.remove("10.1.0.0/16".parse().unwrap()), Some("branch"));
+        assert!(map.is_empty());
+        assert_eq!(map, FcidrMap::new());
+    }
+
+    #[test]
+    fn lookup() {
+        let map = [
+            ("10.0.0.0/8", 8),
+            ("10.0.0.0/16", 16),
+            ("10.0.0.0/24", 24),
+            ("10.0.0.128/25", 25),
+            ("192.168.1.1/32", 32),
+        ]
+        .into_iter()
+        .map(|(cidr, value)| (cidr.parse::<Cidr>().unwrap(), value))
+        .collect::<FcidrMap<_>>();
+        let lookup = |net: &str| {
+            map.lookup(Cidr::from_str_lossy(net).unwrap())
+                .map(|(cidr, value)| (cidr.to_string(), *value))
+        };
+        assert_eq!(
+            lookup("10.0.0.200"),
+            Some(("10.0.0.128/25".to_string(), 25))
+        );
+        assert_eq!(lookup("10.0.0.127"), Some(("10.0.0.0/24".to_string(), 24)));
+        assert_eq!(lookup("10.0.1.0"), Some(("10.0.0.0/16".to_string(), 16)));
+        assert_eq!(
+            lookup("10.255.255.255"),
+            Some(("10.0.0.0/8".to_string(), 8))
+        );
+        assert_eq!(lookup("10.0.0.0/24"), Some(("10.0.0.0/24".to_string(), 24)));
+        assert_eq!(lookup("10.0.0.0/23"), Some(("10.0.0.0/16".to_string(), 16)));
+        assert_eq!(lookup("10.0.0.0/7"), None);
+        assert_eq!(
+            lookup("192.168.1.1"),
+            Some(("192.168.1.1/32".to_string(), 32))
+        );
+        assert_eq!(lookup("192.168.1.0/31"), None);
+        assert_eq!(lookup("11.0.0.0"), None);
+        assert_eq!(
+            map.iter().map(|(_, value)| *value).collect::<Vec<_>>(),
+            [8, 16, 24, 25, 32]
+        );
+    }
+
+    #[test]
+    fn insert_overlapping() {
+        let mut map = FcidrMap::new();
+        // More specific keys first, then the blocks containing them.
+        map.insert("10.1.2.0/24".parse().unwrap(), "c");
+        map.insert("10.1.0.0/16".parse().unwrap(), "b");
+        map.insert("10.0.0.0/8".parse().unwrap(), "a");
+        map.insert("10.1.3.0/24".parse().unwrap(), "d");
+        let lookup = |map: &FcidrMap<&'static str>, ip: &str| {
+            map.lookup(ip.parse::<Ipv4Addr>().unwrap())
+                .map(|(_, value)| *value)
+        };
+        assert_eq!(lookup(&map, "10.1.2.1"), Some("c"));
+        assert_eq!(lookup(&map, "10.1.3.1"), Some("d"));
+        assert_eq!(lookup(&map, "10.1.4.1"), Some("b"));
+        assert_eq!(lookup(&map, "10.2.0.1"), Some("a"));
+        assert_eq!(map.insert("10.1.0.0/16".parse().unwrap(), "e"), Some("b"));
+        assert_eq!(map.len(), 4);
+        assert_eq!(lookup(&map, "10.1.4.1"), Some("e"));
+        assert_eq!(lookup(&map, "10.1.2.1"), Some("c"));
+        *map.get_mut("10.1.2.0/24".parse().unwrap()).unwrap() = "f";
+        assert_eq!(lookup(&map, "10.1.2.1"), Some("f"));
+        assert!(map.contains_key("10.0.0.0/8".parse().unwrap()));
+        assert!(!map.contains_key("10.0.0.0/9".parse().unwrap()));
+        assert_eq!(map.get("10.0.0.0/9".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn remove() {
+        let mut map = FcidrMap::new();
+        map.insert("10.0.0.0/8".parse().unwrap(), 8);
+        map.insert("10.0.0.0/9".parse().unwrap(), 9);
+        map.insert("10.128.0.0/9".parse().unwrap(), 9);
+        // Removing a key not in the map, even one within another, is a no-op.
+        assert_eq!(map.remove("10.1.0.0/16".parse().unwrap()), None);
+        assert_eq!(map.remove("11.0.0.0/8".parse().unwrap()), None);
+        assert_eq!(map.len(), 3);
+        // Removing the covering key leaves the keys within it intact.
+        assert_eq!(map.remove("10.0.0.0/8".parse().unwrap()), Some(8));
+        assert_eq!(
+            map.lookup("10.200.0.0".parse::<Ipv4Addr>().unwrap())
+                .map(|(cidr, _)| cidr.to_string()),
+            Some("10.128.0.0/9".to_string())
+        );
+        assert_eq!(map.lookup("10.0.0.0/8".parse::<Cidr>().unwrap()), None);
+        // Removing the keys within it empties the map entirely.
+        assert_eq!(map.remove("10.0.0.0/9".parse().unwrap()), Some(9));
+        assert_eq!(map.remove("10.128.0.0/9".parse().unwrap()), Some(9));
+        assert!(map.is_empty());
+        assert_eq!(map, FcidrMap::new());
+        map.insert("0.0.0.0/0".parse().unwrap(), 0);
+        map.clear();
+        assert_eq!(map.lookup("1.2.3.4".parse::<Ipv4Addr>().unwrap()), None);
+    }
+}
@@ -0,0 +1,113 @@
+use std::net::Ipv4Addr;
+
+use crate::{trie::Node, Cidr, Fcidr};
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct FcidrMultiset {
+    root: Node<u32>,
+}
+
+impl FcidrMultiset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, cidr: Cidr) -> &mut Self {
+        self.root
+            .update(cidr, &mut |count| *count = count.saturating_add(1));
+        self
+    }
+
+    pub fn remove(&mut self, cidr: Cidr) -> &mut Self {
+        self.root
+            .update(cidr, &mut |count| *count = count.saturating_sub(1));
+        self
+    }
+
+    pub fn coverage_at(&self, ip: Ipv4Addr) -> u32 {
+        self.root.get(ip.into()).copied().unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Cidr, u32)> + '_ {
+        self.root
+            .iter()
+            .filter(|(_, &count)| count > 0)
+            .map(|(cidr, &count)| (cidr, count))
+    }
+
+    pub fn iter_at_least(&self, n: u32) -> impl Iterator<Item = Cidr> + '_ {
+        self.at_least(n).into_iter()
+    }
+
+    pub fn at_least(&self, n: u32) -> Fcidr {
+        let mut fcidr = Fcidr::default();
+        for (cidr, _) in self.iter().filter(|&(_, count)| count >= n) {
+            fcidr.union(cidr);
+        }
+        fcidr
+    }
+}
+
+impl Extend<Cidr> for FcidrMultiset {
+    fn extend<T: IntoIterator<Item = Cidr>>(&mut self, iter: T) {
+        for cidr in iter {
+            self.insert(cidr);
+        }
+    }
+}
+
+impl FromIterator<Cidr> for FcidrMultiset {
+    fn from_iter<T: IntoIterator<Item = Cidr>>(iter: T) -> Self {
+        let mut multiset = Self::new();
+        multiset.extend(iter);
+        multiset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut multiset = ["10.0.0.0/8", "10.1.0.0/16", "10.1.2.0/24", "10.1.3.0/24"]
+            .iter()
+            .map(|c| c.parse().unwrap())
+            .collect::<FcidrMultiset>();
+        assert_eq!(multiset.coverage_at("10.1.2.3".parse().unwrap()), 3);
+        assert_eq!(multiset.coverage_at("10.1.4.3".parse().unwrap()), 2);
+        assert_eq!(multiset.coverage_at("10.2.0.0".parse().unwrap()), 1);
+        assert_eq!(multiset.coverage_at("11.0.0.0".parse().unwrap()), 0);
+        assert_eq!(
+            multiset.iter_at_least(3).collect::<Vec<_>>(),
+            vec!["10.1.2.0/23".parse().unwrap()]
+        );
+        multiset.remove("10.1.0.0/16".parse().unwrap());
+        assert_eq!(multiset.iter_at_least(3).count(), 0);
+        assert_eq!(
+            multiset.iter_at_least(2).collect::<Vec<_>>(),
+            vec!["10.1.2.0/23".parse().unwrap()]
+        );
+        assert_eq!(
+            multiset.iter().collect::<Vec<_>>(),
+            vec![
+                ("10.0.0.0/16".parse().unwrap(), 1),
+                ("10.1.0.0/23".parse().unwrap(), 1),
+                ("10.1.2.0/23".parse().unwrap(), 2),
+                ("10.1.4.0/22".parse().unwrap(), 1),
+                ("10.1.8.0/21".parse().unwrap(), 1),
+                ("10.1.16.0/20".parse().unwrap(), 1),
+                ("10.1.32.0/19".parse().unwrap(), 1),
+                ("10.1.64.0/18".parse().unwrap(), 1),
+                ("10.1.128.0/17".parse().unwrap(), 1),
+                ("10.2.0.0/15".parse().unwrap(), 1),
+                ("10.4.0.0/14".parse().unwrap(), 1),
+                ("10.8.0.0/13".parse().unwrap(), 1),
+                ("10.16.0.0/12".parse().unwrap(), 1),
+                ("10.32.0.0/11".parse().unwrap(), 1),
+                ("10.64.0.0/10".parse().unwrap(), 1),
+                ("10.128.0.0/9".parse().unwrap(), 1),
+            ]
+        );
+    }
+}
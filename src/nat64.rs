@@ -0,0 +1,130 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::{Cidr, Cidr6, Error, Fcidr, Fcidr6};
+
+impl Cidr6 {
+    /// The well-known NAT64 prefix, 64:ff9b::/96 (RFC 6052).
+    pub const NAT64: Cidr6 = Cidr6::from_parts(Ipv6Addr::new(0x64, 0xff9b, 0, 0, 0, 0, 0, 0), 96);
+    /// The prefix of IPv4-mapped IPv6 addresses, ::ffff:0:0/96 (RFC 4291).
+    pub const IPV4_MAPPED: Cidr6 =
+        Cidr6::from_parts(Ipv6Addr::new(0, 0, 0, 0, 0, 0xffff, 0, 0), 96);
+
+    /// Returns the IPv4 block embedded in this block under the /96 `prefix`,
+    /// or `None` if this block is not within `prefix`.
+    pub fn to_ipv4(&self, prefix: Cidr6) -> Result<Option<Cidr>, Error> {
+        check(prefix)?;
+        if !prefix.contains(*self) {
+            return Ok(None);
+        }
+        let network = Ipv4Addr::from(u128::from(self.network()) as u32);
+        Cidr::new(network, self.prefix() - prefix.prefix()).map(Some)
+    }
+}
+
+impl Cidr {
+    /// Returns the block embedding this one under the /96 `prefix`, such as
+    /// 64:ff9b::a00:0/104 for 10.0.0.0/8 under [`Cidr6::NAT64`].
+    pub fn to_ipv6(&self, prefix: Cidr6) -> Result<Cidr6, Error> {
+        check(prefix)?;
+        let network = u128::from(prefix.network()) | u32::from(self.network()) as u128;
+        Cidr6::new(network.into(), prefix.prefix() + self.prefix())
+    }
+}
+
+impl Fcidr {
+    /// Embeds the set under the /96 `prefix`, e.g. to derive the IPv6 ACL of a
+    /// NAT64 gateway from an IPv4 one.
+    pub fn to_ipv6(&self, prefix: Cidr6) -> Result<Fcidr6, Error> {
+        self.iter().map(|cidr| cidr.to_ipv6(prefix)).collect()
+    }
+}
+
+impl Fcidr6 {
+    /// Extracts the IPv4 addresses embedded under the /96 `prefix`, ignoring
+    /// any blocks outside of it.
+    pub fn to_ipv4(&self, prefix: Cidr6) -> Result<Fcidr, Error> {
+        let mut fcidr = Fcidr::default();
+        for cidr in self.iter() {
+            if cidr.contains(prefix) {
+                fcidr.union(Cidr::default());
+            } else if let Some(cidr) = cidr.to_ipv4(prefix)? {
+                fcidr.union(cidr);
+            }
+        }
+        Ok(fcidr)
+    }
+}
+
+fn check(prefix: Cidr6) -> Result<(), Error> {
+    if prefix.prefix() != 96 {
+        return Err(Error::InvalidPrefix(format!(
+            "translation prefix '{prefix}' must be a /96"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(
+            cidr.to_ipv6(Cidr6::NAT64).unwrap().to_string(),
+            "64:ff9b::a00:0/104"
+        );
+        assert_eq!(
+            cidr.to_ipv6(Cidr6::IPV4_MAPPED).unwrap().to_string(),
+            "::ffff:10.0.0.0/104"
+        );
+        assert!(matches!(
+            cidr.to_ipv6("2001:db8::/64".parse().unwrap()),
+            Err(Error::InvalidPrefix(_))
+        ));
+        let mut fcidr = Fcidr::new(cidr);
+        fcidr.difference("10.1.0.0/16".parse().unwrap());
+        let fcidr6 = fcidr.to_ipv6(Cidr6::NAT64).unwrap();
+        assert_eq!(fcidr6.iter().count(), fcidr.iter().count());
+        assert!(fcidr6.is_superset("64:ff9b::a02:0/112".parse().unwrap()));
+        assert!(!fcidr6.is_superset("64:ff9b::a01:0/112".parse().unwrap()));
+        assert_eq!(
+            fcidr6
+                .to_ipv4(Cidr6::NAT64)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            fcidr.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            fcidr6.to_ipv4(Cidr6::IPV4_MAPPED).unwrap().iter().count(),
+            0
+        );
+        let everything = Fcidr6::new("::/0".parse().unwrap());
+        assert_eq!(
+            everything
+                .to_ipv4(Cidr6::NAT64)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![Cidr::default()]
+        );
+        assert_eq!(
+            "64:ff9b::/96"
+                .parse::<Cidr6>()
+                .unwrap()
+                .to_ipv4(Cidr6::NAT64)
+                .unwrap(),
+            Some(Cidr::default())
+        );
+        assert_eq!(
+            "2001:db8::/96"
+                .parse::<Cidr6>()
+                .unwrap()
+                .to_ipv4(Cidr6::NAT64)
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use std::ops::Deref;
+
+use crate::{Cidr, Fcidr};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Change {
+    Included(Cidr),
+    Excluded(Cidr),
+}
+
+#[derive(Debug)]
+pub struct ObservedFcidr<F>
+where
+    F: FnMut(Change),
+{
+    fcidr: Fcidr,
+    observer: F,
+}
+
+impl<F> ObservedFcidr<F>
+where
+    F: FnMut(Change),
+{
+    pub fn new(fcidr: Fcidr, observer: F) -> Self {
+        Self { fcidr, observer }
+    }
+
+    pub fn complement(&mut self) -> &mut Self {
+        for cidr in &self.fcidr {
+            (self.observer)(Change::Excluded(cidr));
+        }
+        self.fcidr.complement();
+        for cidr in &self.fcidr {
+            (self.observer)(Change::Included(cidr));
+        }
+        self
+    }
+
+    pub fn difference(&mut self, cidr: Cidr) -> &mut Self {
+        let excluded = self.fcidr.covered_within(cidr);
+        self.fcidr.difference(cidr);
+        for cidr in excluded {
+            (self.observer)(Change::Excluded(cidr));
+        }
+        self
+    }
+
+    pub fn union(&mut self, cidr: Cidr) -> &mut Self {
+        let mut included = Fcidr::new(cidr);
+        for c in self.fcidr.covered_within(cidr) {
+            included.difference(c);
+        }
+        self.fcidr.union(cidr);
+        for cidr in &included {
+            (self.observer)(Change::Included(cidr));
+        }
+        self
+    }
+
+    pub fn into_inner(self) -> Fcidr {
+        self.fcidr
+    }
+}
+
+impl<F> Deref for ObservedFcidr<F>
+where
+    F: FnMut(Change),
+{
+    type Target = Fcidr;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fcidr
+    }
+}
+
+impl Fcidr {
+    pub fn observe<F>(self, observer: F) -> ObservedFcidr<F>
+    where
+        F: FnMut(Change),
+    {
+        ObservedFcidr::new(self, observer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap()).observe({
+            let changes = changes.clone();
+            move |change| changes.borrow_mut().push(change)
+        });
+        fcidr.union("10.0.0.0/7".parse().unwrap());
+        assert_eq!(
+            changes.take(),
+            vec![Change::Included("11.0.0.0/8".parse().unwrap())]
+        );
+        fcidr.difference("11.0.0.0/9".parse().unwrap());
+        assert_eq!(
+            changes.take(),
+            vec![Change::Excluded("11.0.0.0/9".parse().unwrap())]
+        );
+        fcidr.difference("12.0.0.0/8".parse().unwrap());
+        assert_eq!(changes.take(), vec![]);
+        fcidr.union("8.0.0.0/6".parse().unwrap());
+        assert_eq!(
+            changes.take(),
+            vec![
+                Change::Included("8.0.0.0/7".parse().unwrap()),
+                Change::Included("11.0.0.0/9".parse().unwrap()),
+            ]
+        );
+        fcidr.complement();
+        let changes = changes.take();
+        assert_eq!(changes[0], Change::Excluded("8.0.0.0/6".parse().unwrap()));
+        assert_eq!(changes.len(), 7);
+        assert!(!fcidr.is_superset("8.0.0.0/6".parse().unwrap()));
+    }
+}
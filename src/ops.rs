@@ -0,0 +1,188 @@
+//! Set algebra through the operators: `|` union, `&` intersection, `-`
+//! difference, `^` symmetric difference and `!` complement. Owned left
+//! operands are updated in place, while borrowed ones are left untouched.
+
+use std::ops::{BitAnd, BitOr, BitXor, Not, Sub};
+
+use crate::{Cidr, Fcidr};
+
+impl BitOr<&Fcidr> for Fcidr {
+    type Output = Fcidr;
+
+    fn bitor(mut self, rhs: &Fcidr) -> Fcidr {
+        self.union_set(rhs);
+        self
+    }
+}
+
+impl BitOr<Cidr> for Fcidr {
+    type Output = Fcidr;
+
+    fn bitor(mut self, rhs: Cidr) -> Fcidr {
+        self.union(rhs);
+        self
+    }
+}
+
+impl BitAnd<&Fcidr> for Fcidr {
+    type Output = Fcidr;
+
+    fn bitand(mut self, rhs: &Fcidr) -> Fcidr {
+        self.difference_set(&rhs.complemented());
+        self
+    }
+}
+
+impl BitAnd<Cidr> for Fcidr {
+    type Output = Fcidr;
+
+    fn bitand(mut self, rhs: Cidr) -> Fcidr {
+        self.restrict(rhs);
+        self
+    }
+}
+
+impl Sub<&Fcidr> for Fcidr {
+    type Output = Fcidr;
+
+    fn sub(mut self, rhs: &Fcidr) -> Fcidr {
+        self.difference_set(rhs);
+        self
+    }
+}
+
+impl Sub<Cidr> for Fcidr {
+    type Output = Fcidr;
+
+    fn sub(mut self, rhs: Cidr) -> Fcidr {
+        self.difference(rhs);
+        self
+    }
+}
+
+impl BitXor<&Fcidr> for Fcidr {
+    type Output = Fcidr;
+
+    fn bitxor(mut self, rhs: &Fcidr) -> Fcidr {
+        for cidr in rhs {
+            self.symmetric_difference(cidr);
+        }
+        self
+    }
+}
+
+impl BitXor<Cidr> for Fcidr {
+    type Output = Fcidr;
+
+    fn bitxor(mut self, rhs: Cidr) -> Fcidr {
+        self.symmetric_difference(rhs);
+        self
+    }
+}
+
+impl Not for Fcidr {
+    type Output = Fcidr;
+
+    fn not(mut self) -> Fcidr {
+        self.complement();
+        self
+    }
+}
+
+impl Not for &Fcidr {
+    type Output = Fcidr;
+
+    fn not(self) -> Fcidr {
+        self.complemented()
+    }
+}
+
+// Borrowed left operands are copied, as by the `*ed` methods, and the copy
+// updated in place.
+macro_rules! forward_borrowed {
+    ($($trait:ident::$method:ident),*) => {$(
+        impl $trait<Fcidr> for Fcidr {
+            type Output = Fcidr;
+
+            fn $method(self, rhs: Fcidr) -> Fcidr {
+                self.$method(&rhs)
+            }
+        }
+
+        impl $trait<&Fcidr> for &Fcidr {
+            type Output = Fcidr;
+
+            fn $method(self, rhs: &Fcidr) -> Fcidr {
+                self.clone().$method(rhs)
+            }
+        }
+
+        impl $trait<Fcidr> for &Fcidr {
+            type Output = Fcidr;
+
+            fn $method(self, rhs: Fcidr) -> Fcidr {
+                self.clone().$method(&rhs)
+            }
+        }
+
+        impl $trait<Cidr> for &Fcidr {
+            type Output = Fcidr;
+
+            fn $method(self, rhs: Cidr) -> Fcidr {
+                self.clone().$method(rhs)
+            }
+        }
+    )*};
+}
+
+forward_borrowed!(BitOr::bitor, BitAnd::bitand, Sub::sub, BitXor::bitxor);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidrs(fcidr: &Fcidr) -> Vec<String> {
+        fcidr.iter().map(|cidr| cidr.to_string()).collect()
+    }
+
+    #[test]
+    fn it_works() {
+        let allow = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        let extra = Fcidr::new("11.0.0.0/8".parse().unwrap());
+        let blocked = Fcidr::new("11.0.0.0/9".parse().unwrap());
+        assert_eq!(
+            cidrs(&(&allow | (&extra - &blocked))),
+            ["10.0.0.0/8", "11.128.0.0/9"]
+        );
+        assert_eq!(
+            cidrs(&(&allow | &extra & &blocked)),
+            ["10.0.0.0/8", "11.0.0.0/9"]
+        );
+        assert_eq!(
+            cidrs(&(&allow ^ &extra ^ &blocked)),
+            ["10.0.0.0/8", "11.128.0.0/9"]
+        );
+        assert_eq!(
+            cidrs(&(&allow & "10.1.0.0/16".parse::<Cidr>().unwrap())),
+            ["10.1.0.0/16"]
+        );
+        assert_eq!(
+            cidrs(&(&allow - "10.0.0.0/9".parse::<Cidr>().unwrap())),
+            ["10.128.0.0/9"]
+        );
+        assert_eq!(
+            cidrs(&(allow.clone() ^ "10.0.0.0/7".parse::<Cidr>().unwrap())),
+            ["11.0.0.0/8"]
+        );
+        assert_eq!(
+            cidrs(&(&allow | "11.0.0.0/8".parse::<Cidr>().unwrap())),
+            ["10.0.0.0/7"]
+        );
+        assert_eq!(cidrs(&!(!&allow)), ["10.0.0.0/8"]);
+        assert_eq!(cidrs(&(!allow.clone() & &allow)), Vec::<String>::new());
+        let shared = allow.clone();
+        let result = shared | extra;
+        assert_eq!(cidrs(&result), ["10.0.0.0/7"]);
+        assert_eq!(cidrs(&allow), ["10.0.0.0/8"]);
+    }
+}
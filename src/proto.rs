@@ -0,0 +1,100 @@
+#![cfg(feature = "prost")]
+#![cfg_attr(doc_cfg, doc(cfg(feature = "prost")))]
+
+use std::net::Ipv4Addr;
+
+use crate::{Error, Fcidr};
+
+// Keep in sync with proto/fcidr.proto.
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq, prost::Message)]
+pub struct Cidr {
+    #[prost(uint32, tag = "1")]
+    pub network: u32,
+    #[prost(uint32, tag = "2")]
+    pub prefix: u32,
+}
+
+#[derive(Clone, Eq, Hash, PartialEq, prost::Message)]
+pub struct CidrSet {
+    #[prost(message, repeated, tag = "1")]
+    pub cidrs: Vec<Cidr>,
+}
+
+impl From<crate::Cidr> for Cidr {
+    fn from(value: crate::Cidr) -> Self {
+        Self {
+            network: value.network().into(),
+            prefix: value.prefix().into(),
+        }
+    }
+}
+
+impl TryFrom<Cidr> for crate::Cidr {
+    type Error = Error;
+
+    fn try_from(value: Cidr) -> Result<Self, Self::Error> {
+        let prefix = u8::try_from(value.prefix).map_err(|_| {
+            Error::InvalidPrefix(format!(
+                "network prefix '{}' must be 32 or less",
+                value.prefix
+            ))
+        })?;
+        Self::new(Ipv4Addr::from(value.network), prefix)
+    }
+}
+
+impl From<&Fcidr> for CidrSet {
+    fn from(value: &Fcidr) -> Self {
+        Self {
+            cidrs: value.iter().map(Cidr::from).collect(),
+        }
+    }
+}
+
+impl From<Fcidr> for CidrSet {
+    fn from(value: Fcidr) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl TryFrom<CidrSet> for Fcidr {
+    type Error = Error;
+
+    fn try_from(value: CidrSet) -> Result<Self, Self::Error> {
+        let mut fcidr = Self::default();
+        for cidr in value.cidrs {
+            fcidr.union(cidr.try_into()?);
+        }
+        Ok(fcidr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.difference("10.0.64.0/20".parse().unwrap());
+        let bytes = CidrSet::from(&fcidr).encode_to_vec();
+        let decoded = Fcidr::try_from(CidrSet::decode(bytes.as_slice()).unwrap()).unwrap();
+        assert_eq!(
+            decoded.iter().collect::<Vec<_>>(),
+            fcidr.iter().collect::<Vec<_>>()
+        );
+        assert!(crate::Cidr::try_from(Cidr {
+            network: 0x0a000001,
+            prefix: 8
+        })
+        .is_err());
+        assert!(crate::Cidr::try_from(Cidr {
+            network: 0,
+            prefix: 256
+        })
+        .is_err());
+    }
+}
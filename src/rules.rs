@@ -0,0 +1,146 @@
+use std::{fmt, net::Ipv4Addr, str::FromStr};
+
+use crate::{Cidr, Error, Fcidr, FcidrMap};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        })
+    }
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "deny" => Ok(Self::Deny),
+            _ => Err(Error::Parse("expected 'allow' or 'deny'".to_string())),
+        }
+    }
+}
+
+/// An ordered list of allow and deny rules in which the first rule matching
+/// an address decides it, and addresses matching no rule are denied.
+#[derive(Clone, Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<(Action, Cidr)>,
+    allowed: Fcidr,
+    decisions: FcidrMap<usize>,
+}
+
+impl RuleSet {
+    pub fn new<I>(rules: I) -> Self
+    where
+        I: IntoIterator<Item = (Action, Cidr)>,
+    {
+        let rules = rules.into_iter().collect::<Vec<_>>();
+        let mut decided = Fcidr::default();
+        let mut allowed = Fcidr::default();
+        let mut decisions = FcidrMap::new();
+        for (index, &(action, cidr)) in rules.iter().enumerate() {
+            let mut undecided = Fcidr::new(cidr);
+            undecided.difference_set(&decided);
+            for block in &undecided {
+                decisions.insert(block, index);
+            }
+            if action == Action::Allow {
+                allowed.union_set(&undecided);
+            }
+            decided.union(cidr);
+        }
+        Self {
+            rules,
+            allowed,
+            decisions,
+        }
+    }
+
+    pub fn rules(&self) -> &[(Action, Cidr)] {
+        &self.rules
+    }
+
+    /// The addresses the rules allow.
+    pub fn allowed(&self) -> &Fcidr {
+        &self.allowed
+    }
+
+    /// The action taken for the address, and the index of the rule deciding
+    /// it, if any.
+    pub fn decision(&self, ip: Ipv4Addr) -> (Action, Option<usize>) {
+        match self.decisions.lookup(ip) {
+            Some((_, &index)) => (self.rules[index].0, Some(index)),
+            None => (Action::Deny, None),
+        }
+    }
+
+    /// The indices of the rules deciding no address, because earlier rules
+    /// already match all of theirs.
+    pub fn shadowed(&self) -> Vec<usize> {
+        let mut decides = vec![false; self.rules.len()];
+        for (_, &index) in &self.decisions {
+            decides[index] = true;
+        }
+        (0..self.rules.len()).filter(|&i| !decides[i]).collect()
+    }
+}
+
+impl FromIterator<(Action, Cidr)> for RuleSet {
+    fn from_iter<T: IntoIterator<Item = (Action, Cidr)>>(iter: T) -> Self {
+        Self::new(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let rules = [
+            "allow 10.0.0.0/8",
+            "deny 10.1.0.0/16",
+            "deny 192.168.0.0/16",
+            "allow 192.168.1.0/24",
+            "allow 192.168.0.0/24",
+            "deny 172.16.0.0/12",
+        ]
+        .iter()
+        .map(|rule| {
+            let (action, cidr) = rule.split_once(' ').unwrap();
+            (action.parse().unwrap(), cidr.parse().unwrap())
+        })
+        .collect::<RuleSet>();
+        assert_eq!(
+            rules.allowed().iter().collect::<Vec<_>>(),
+            vec!["10.0.0.0/8".parse().unwrap()]
+        );
+        assert_eq!(rules.shadowed(), vec![1, 3, 4]);
+        assert_eq!(
+            rules.decision("10.1.2.3".parse().unwrap()),
+            (Action::Allow, Some(0))
+        );
+        assert_eq!(
+            rules.decision("192.168.1.1".parse().unwrap()),
+            (Action::Deny, Some(2))
+        );
+        assert_eq!(
+            rules.decision("172.31.255.255".parse().unwrap()),
+            (Action::Deny, Some(5))
+        );
+        assert_eq!(
+            rules.decision("8.8.8.8".parse().unwrap()),
+            (Action::Deny, None)
+        );
+        assert!("permit".parse::<Action>().is_err());
+    }
+}
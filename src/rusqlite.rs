@@ -0,0 +1,104 @@
+#![cfg(feature = "rusqlite")]
+#![cfg_attr(doc_cfg, doc(cfg(feature = "rusqlite")))]
+
+use std::net::Ipv4Addr;
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+use crate::{Cidr, Fcidr};
+
+const CIDR_BLOB_SIZE: usize = 5;
+
+fn cidr_from_blob(blob: &[u8]) -> FromSqlResult<Cidr> {
+    match blob {
+        &[a, b, c, d, prefix] => {
+            Cidr::new(Ipv4Addr::new(a, b, c, d), prefix).map_err(|e| FromSqlError::Other(e.into()))
+        }
+        _ => Err(FromSqlError::InvalidBlobSize {
+            expected_size: CIDR_BLOB_SIZE,
+            blob_size: blob.len(),
+        }),
+    }
+}
+
+fn cidr_to_blob(cidr: Cidr) -> [u8; CIDR_BLOB_SIZE] {
+    let [a, b, c, d] = cidr.network().octets();
+    [a, b, c, d, cidr.prefix()]
+}
+
+impl FromSql for Cidr {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Text(text) => std::str::from_utf8(text)
+                .map_err(|e| FromSqlError::Other(e.into()))?
+                .parse()
+                .map_err(|e: crate::Error| FromSqlError::Other(e.into())),
+            ValueRef::Blob(blob) => cidr_from_blob(blob),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for Cidr {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for Fcidr {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let blob = value.as_blob()?;
+        if blob.len() % CIDR_BLOB_SIZE != 0 {
+            return Err(FromSqlError::InvalidBlobSize {
+                expected_size: blob.len() / CIDR_BLOB_SIZE * CIDR_BLOB_SIZE,
+                blob_size: blob.len(),
+            });
+        }
+        let mut fcidr = Self::default();
+        for chunk in blob.chunks_exact(CIDR_BLOB_SIZE) {
+            fcidr.union(cidr_from_blob(chunk)?);
+        }
+        Ok(fcidr)
+    }
+}
+
+impl ToSql for Fcidr {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(
+            self.iter().flat_map(cidr_to_blob).collect::<Vec<_>>(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE allowlist (cidr TEXT, fcidr BLOB)", ())
+            .unwrap();
+        let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+        let mut fcidr = Fcidr::new(cidr);
+        fcidr.difference("10.0.64.0/20".parse().unwrap());
+        conn.execute("INSERT INTO allowlist VALUES (?1, ?2)", (cidr, &fcidr))
+            .unwrap();
+        let (c, f): (Cidr, Fcidr) = conn
+            .query_row("SELECT cidr, fcidr FROM allowlist", (), |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(c, cidr);
+        assert_eq!(
+            f.iter().collect::<Vec<_>>(),
+            fcidr.iter().collect::<Vec<_>>()
+        );
+        let c: Cidr = conn
+            .query_row("SELECT x'0a80000009'", (), |row| row.get(0))
+            .unwrap();
+        assert_eq!(c, "10.128.0.0/9".parse().unwrap());
+    }
+}
@@ -1,11 +1,15 @@
 #![cfg(feature = "serde")]
 #![cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
 
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
 
-use serde::{de::Visitor, ser::SerializeSeq, Deserialize, Serialize};
+use serde::{
+    de::Visitor,
+    ser::{SerializeSeq, SerializeStruct},
+    Deserialize, Serialize,
+};
 
-use crate::{Cidr, Fcidr};
+use crate::{Cidr, Fcidr, Ipam};
 
 struct CidrVisitor;
 
@@ -85,6 +89,339 @@ impl Serialize for Fcidr {
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename = "Ipam", deny_unknown_fields)]
+struct IpamState {
+    universe: Fcidr,
+    allocations: BTreeMap<Cidr, String>,
+}
+
+impl<'de> Deserialize<'de> for Ipam {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let state = IpamState::deserialize(deserializer)?;
+        let mut ipam = Self::new(state.universe);
+        for (cidr, label) in state.allocations {
+            ipam.reserve(cidr, label)
+                .map_err(serde::de::Error::custom)?;
+        }
+        Ok(ipam)
+    }
+}
+
+impl Serialize for Ipam {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("Ipam", 2)?;
+        state.serialize_field("universe", self.universe())?;
+        state.serialize_field(
+            "allocations",
+            &self.allocations().collect::<BTreeMap<_, _>>(),
+        )?;
+        state.end()
+    }
+}
+
+/// Like the default representation of [`Fcidr`], but deserialization fails if
+/// any element overlaps or duplicates an earlier one, for use with
+/// `#[serde(with = "fcidr::serde::strict")]`.
+pub mod strict {
+    use std::collections::BTreeSet;
+
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        Deserializer, Serialize, Serializer,
+    };
+
+    use crate::{Cidr, Fcidr};
+
+    struct FcidrVisitor;
+
+    impl<'de> Visitor<'de> for FcidrVisitor {
+        type Value = Fcidr;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a list of disjoint cidr blocks")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut value = Self::Value::default();
+            // The set aggregates siblings, so keep the elements as given to
+            // name the one an overlap is with.
+            let mut elements = BTreeSet::new();
+            while let Some(element) = seq.next_element::<Cidr>()? {
+                // Ordered by network then prefix, an earlier element within
+                // this one comes right after it.
+                let overlap = std::iter::once(element)
+                    .chain(element.ancestors())
+                    .find(|cidr| elements.contains(cidr))
+                    .or_else(|| {
+                        elements
+                            .range(element..)
+                            .next()
+                            .copied()
+                            .filter(|&cidr| element.contains(cidr))
+                    });
+                if let Some(overlap) = overlap {
+                    return Err(de::Error::custom(format!(
+                        "cidr block '{element}' overlaps earlier element '{overlap}'"
+                    )));
+                }
+                elements.insert(element);
+                value.union(element);
+            }
+            Ok(value)
+        }
+    }
+
+    pub fn serialize<S>(fcidr: &Fcidr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        fcidr.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Fcidr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(FcidrVisitor)
+    }
+}
+
+/// Represents an [`Fcidr`] as a single string of comma separated cidr blocks,
+/// such as `"10.0.0.0/8,192.168.0.0/16"`, for use with
+/// `#[serde(with = "fcidr::serde::comma_separated")]`. Whitespace around each
+/// block is ignored.
+pub mod comma_separated {
+    use serde::{Deserializer, Serializer};
+
+    use crate::Fcidr;
+
+    pub fn serialize<S>(fcidr: &Fcidr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        super::delimited::serialize(fcidr, ",", serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Fcidr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::delimited::deserialize(deserializer, |c| c == ',')
+    }
+}
+
+/// Represents an [`Fcidr`] as a single string of whitespace separated cidr
+/// blocks, such as `"10.0.0.0/8 192.168.0.0/16"`, for use with
+/// `#[serde(with = "fcidr::serde::space_separated")]`.
+pub mod space_separated {
+    use serde::{Deserializer, Serializer};
+
+    use crate::Fcidr;
+
+    pub fn serialize<S>(fcidr: &Fcidr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        super::delimited::serialize(fcidr, " ", serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Fcidr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::delimited::deserialize(deserializer, char::is_whitespace)
+    }
+}
+
+mod delimited {
+    use serde::{
+        de::{self, Visitor},
+        Deserializer, Serializer,
+    };
+
+    use crate::{Cidr, Fcidr};
+
+    struct FcidrVisitor<F>(F);
+
+    impl<'de, F> Visitor<'de> for FcidrVisitor<F>
+    where
+        F: Fn(char) -> bool,
+    {
+        type Value = Fcidr;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a string of delimited cidr blocks")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            v.split(&self.0)
+                .map(str::trim)
+                .filter(|token| !token.is_empty())
+                .map(|token| token.parse::<Cidr>().map_err(de::Error::custom))
+                .collect()
+        }
+    }
+
+    pub(super) fn serialize<S>(
+        fcidr: &Fcidr,
+        delimiter: &str,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let cidrs = fcidr
+            .iter()
+            .map(|cidr| cidr.to_string())
+            .collect::<Vec<_>>();
+        serializer.serialize_str(&cidrs.join(delimiter))
+    }
+
+    pub(super) fn deserialize<'de, D, F>(
+        deserializer: D,
+        is_delimiter: F,
+    ) -> Result<Fcidr, D::Error>
+    where
+        D: Deserializer<'de>,
+        F: Fn(char) -> bool,
+    {
+        deserializer.deserialize_str(FcidrVisitor(is_delimiter))
+    }
+}
+
+/// Represents a [`Cidr`] as a struct of its network address and prefix length,
+/// such as `{ "network": "10.0.0.0", "prefix": 8 }`, for use with
+/// `#[serde(with = "fcidr::serde::structured")]`. Host bits set in the network
+/// are rejected.
+pub mod structured {
+    use std::net::Ipv4Addr;
+
+    use serde::{
+        de::{self, MapAccess, SeqAccess, Visitor},
+        ser::SerializeStruct,
+        Deserializer, Serializer,
+    };
+
+    use crate::Cidr;
+
+    const FIELDS: &[&str] = &["network", "prefix"];
+
+    enum Field {
+        Network,
+        Prefix,
+    }
+
+    struct FieldVisitor;
+
+    impl<'de> Visitor<'de> for FieldVisitor {
+        type Value = Field;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("`network` or `prefix`")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            match v {
+                "network" => Ok(Field::Network),
+                "prefix" => Ok(Field::Prefix),
+                _ => Err(de::Error::unknown_field(v, FIELDS)),
+            }
+        }
+    }
+
+    impl<'de> serde::Deserialize<'de> for Field {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_identifier(FieldVisitor)
+        }
+    }
+
+    struct CidrVisitor;
+
+    impl<'de> Visitor<'de> for CidrVisitor {
+        type Value = Cidr;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a cidr block with a network and prefix")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let network: Ipv4Addr = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let prefix: u8 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            Cidr::new(network, prefix).map_err(de::Error::custom)
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut network = None;
+            let mut prefix = None;
+            while let Some(key) = map.next_key()? {
+                match key {
+                    Field::Network => {
+                        if network.is_some() {
+                            return Err(de::Error::duplicate_field("network"));
+                        }
+                        network = Some(map.next_value::<Ipv4Addr>()?);
+                    }
+                    Field::Prefix => {
+                        if prefix.is_some() {
+                            return Err(de::Error::duplicate_field("prefix"));
+                        }
+                        prefix = Some(map.next_value::<u8>()?);
+                    }
+                }
+            }
+            let network = network.ok_or_else(|| de::Error::missing_field("network"))?;
+            let prefix = prefix.ok_or_else(|| de::Error::missing_field("prefix"))?;
+            Cidr::new(network, prefix).map_err(de::Error::custom)
+        }
+    }
+
+    pub fn serialize<S>(cidr: &Cidr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Cidr", FIELDS.len())?;
+        state.serialize_field("network", &cidr.network())?;
+        state.serialize_field("prefix", &cidr.prefix())?;
+        state.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Cidr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Cidr", FIELDS, CidrVisitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,4 +442,112 @@ mod tests {
             println!("{i} - {cidr}");
         }
     }
+
+    #[test]
+    fn strict() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct Config {
+            #[serde(with = "crate::serde::strict")]
+            allow: Fcidr,
+        }
+        let config: Config =
+            serde_json::from_str(r#"{"allow":["10.0.0.0/8","11.0.0.0/8"]}"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"allow":["10.0.0.0/7"]}"#
+        );
+        for (allow, message) in [
+            (
+                r#"["10.0.0.0/8","10.0.0.0/8"]"#,
+                "cidr block '10.0.0.0/8' overlaps earlier element '10.0.0.0/8'",
+            ),
+            (
+                r#"["10.0.0.0/8","10.1.0.0/16"]"#,
+                "cidr block '10.1.0.0/16' overlaps earlier element '10.0.0.0/8'",
+            ),
+            (
+                r#"["10.1.0.0/16","10.0.0.0/8"]"#,
+                "cidr block '10.0.0.0/8' overlaps earlier element '10.1.0.0/16'",
+            ),
+            (
+                r#"["10.0.0.0/9","10.128.0.0/9","10.1.0.0/16"]"#,
+                "cidr block '10.1.0.0/16' overlaps earlier element '10.0.0.0/9'",
+            ),
+            (
+                r#"["10.2.0.0/16","10.3.0.0/16","10.0.0.0/14"]"#,
+                "cidr block '10.0.0.0/14' overlaps earlier element '10.2.0.0/16'",
+            ),
+        ] {
+            let error =
+                serde_json::from_str::<Config>(&format!(r#"{{"allow":{allow}}}"#)).unwrap_err();
+            assert!(error.to_string().starts_with(message), "{error}");
+        }
+    }
+
+    #[test]
+    fn delimited() {
+        #[derive(Debug, Deserialize, Serialize)]
+        struct Config {
+            #[serde(with = "crate::serde::comma_separated")]
+            allow: Fcidr,
+            #[serde(with = "crate::serde::space_separated")]
+            deny: Fcidr,
+        }
+        let config: Config = serde_json::from_str(
+            r#"{"allow":"10.0.0.0/8, 11.0.0.0/8,,192.168.0.0/16","deny":" 10.1.0.0/16\t10.2.0.0/16 "}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"allow":"10.0.0.0/7,192.168.0.0/16","deny":"10.1.0.0/16 10.2.0.0/16"}"#
+        );
+        let config: Config = serde_json::from_str(r#"{"allow":"","deny":""}"#).unwrap();
+        assert_eq!(config.allow.iter().count(), 0);
+        assert!(serde_json::from_str::<Config>(r#"{"allow":"10.0.0.0/33","deny":""}"#).is_err());
+        assert!(serde_json::from_str::<Config>(r#"{"allow":["10.0.0.0/8"],"deny":""}"#).is_err());
+    }
+
+    #[test]
+    fn ipam() {
+        let mut ipam = Ipam::new("10.0.0.0/16".parse::<Cidr>().unwrap());
+        ipam.allocate(24, "web").unwrap();
+        ipam.allocate(20, "k8s").unwrap();
+        let json = serde_json::to_value(&ipam).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "universe": ["10.0.0.0/16"],
+                "allocations": { "10.0.0.0/24": "web", "10.0.16.0/20": "k8s" },
+            })
+        );
+        let ipam: Ipam = serde_json::from_value(json).unwrap();
+        assert_eq!(ipam.get("10.0.16.0/20".parse().unwrap()), Some("k8s"));
+        assert!(!ipam.free().is_superset("10.0.0.0/24".parse().unwrap()));
+        assert!(serde_json::from_value::<Ipam>(serde_json::json!({
+            "universe": ["10.0.0.0/16"],
+            "allocations": { "10.0.0.0/24": "web", "10.0.0.0/25": "db" },
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn structured() {
+        #[derive(Debug, Deserialize, PartialEq, Serialize)]
+        struct Rule {
+            #[serde(with = "structured")]
+            cidr: Cidr,
+        }
+
+        let rule = Rule {
+            cidr: "10.0.0.0/8".parse().unwrap(),
+        };
+        let json = serde_json::json!({ "cidr": { "network": "10.0.0.0", "prefix": 8 } });
+        assert_eq!(serde_json::to_value(&rule).unwrap(), json);
+        assert_eq!(serde_json::from_value::<Rule>(json).unwrap(), rule);
+        assert!(serde_json::from_str::<Rule>(
+            r#"{ "cidr": { "network": "10.0.0.1", "prefix": 8 } }"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Rule>(r#"{ "cidr": { "network": "10.0.0.0" } }"#).is_err());
+    }
 }
@@ -0,0 +1,97 @@
+#![cfg(feature = "sqlx-postgres")]
+#![cfg_attr(doc_cfg, doc(cfg(feature = "sqlx-postgres")))]
+
+use std::net::Ipv4Addr;
+
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    postgres::{PgArgumentBuffer, PgHasArrayType, PgTypeInfo, PgValueFormat, PgValueRef},
+    Decode, Encode, Postgres, Type,
+};
+
+use crate::Cidr;
+
+// https://github.com/postgres/postgres/blob/REL_16_0/src/include/utils/inet.h#L33
+const PGSQL_AF_INET: u8 = 2;
+
+impl Type<Postgres> for Cidr {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("cidr")
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        *ty == PgTypeInfo::with_name("cidr") || *ty == PgTypeInfo::with_name("inet")
+    }
+}
+
+impl PgHasArrayType for Cidr {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_cidr")
+    }
+
+    fn array_compatible(ty: &PgTypeInfo) -> bool {
+        *ty == PgTypeInfo::with_name("_cidr") || *ty == PgTypeInfo::with_name("_inet")
+    }
+}
+
+impl Encode<'_, Postgres> for Cidr {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        // https://github.com/postgres/postgres/blob/REL_16_0/src/backend/utils/adt/network.c#L270
+        buf.push(PGSQL_AF_INET);
+        buf.push(self.prefix());
+        buf.push(1);
+        buf.push(4);
+        buf.extend_from_slice(&self.network().octets());
+        Ok(IsNull::No)
+    }
+
+    fn size_hint(&self) -> usize {
+        8
+    }
+}
+
+/// Decodes the binary form of a `cidr` or `inet` value. An `inet` may have
+/// host bits set, as in `10.1.2.3/24`, which are cleared.
+fn decode(bytes: &[u8]) -> Result<Cidr, BoxDynError> {
+    match bytes {
+        [PGSQL_AF_INET, prefix, 1, 4, a, b, c, d] => {
+            Ok(Cidr::new(Ipv4Addr::new(*a, *b, *c, *d), *prefix)?)
+        }
+        [PGSQL_AF_INET, prefix, 0, 4, a, b, c, d] => {
+            Ok(Cidr::new_truncate(Ipv4Addr::new(*a, *b, *c, *d), *prefix)?)
+        }
+        [family, ..] if *family != PGSQL_AF_INET => {
+            Err(format!("unsupported ip family {family}").into())
+        }
+        _ => Err("invalid data received when expecting a CIDR".into()),
+    }
+}
+
+impl Decode<'_, Postgres> for Cidr {
+    fn decode(value: PgValueRef<'_>) -> Result<Self, BoxDynError> {
+        match value.format() {
+            PgValueFormat::Binary => decode(value.as_bytes()?),
+            // Only an inet can have host bits set, so clearing them leaves a
+            // cidr untouched.
+            PgValueFormat::Text => Ok(Cidr::from_str_truncate(value.as_str()?)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let cidr: Cidr = "10.1.0.0/16".parse().unwrap();
+        let mut buf = PgArgumentBuffer::default();
+        assert!(matches!(cidr.encode_by_ref(&mut buf), Ok(IsNull::No)));
+        assert_eq!(decode(&buf).unwrap(), cidr);
+        assert_eq!(decode(&[2, 16, 0, 4, 10, 1, 2, 3]).unwrap(), cidr);
+        assert!(decode(&[2, 16, 1, 4, 10, 1, 2, 3]).is_err());
+        assert!(decode(&[2, 33, 0, 4, 10, 1, 2, 3]).is_err());
+        assert!(decode(&[3, 64, 0, 16]).is_err());
+    }
+}
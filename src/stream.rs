@@ -0,0 +1,114 @@
+//! Adapters over streams of CIDRs too large to collect into an [`Fcidr`].
+//!
+//! [`Fcidr`]: crate::Fcidr
+
+use std::mem;
+
+use crate::Cidr;
+
+/// Aggregates CIDRs sorted in ascending order into the fewest blocks covering
+/// them, yielding each block as soon as no later input can extend it.
+///
+/// Memory use is bounded by the prefix length rather than the input size.
+/// Unsorted input yields a correct but not necessarily minimal cover.
+#[derive(Clone, Debug)]
+pub struct Aggregate<I> {
+    iter: I,
+    stack: Vec<Cidr>,
+    ready: std::vec::IntoIter<Cidr>,
+}
+
+impl<I> Aggregate<I>
+where
+    I: Iterator<Item = Cidr>,
+{
+    pub fn new<T>(iter: T) -> Self
+    where
+        T: IntoIterator<IntoIter = I>,
+    {
+        Self {
+            iter: iter.into_iter(),
+            stack: Vec::new(),
+            ready: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<I> Iterator for Aggregate<I>
+where
+    I: Iterator<Item = Cidr>,
+{
+    type Item = Cidr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(cidr) = self.ready.next() {
+                return Some(cidr);
+            }
+            let Some(cidr) = self.iter.next() else {
+                self.ready = mem::take(&mut self.stack).into_iter();
+                return self.ready.next();
+            };
+            match self.stack.last() {
+                Some(top) if top.contains(cidr) => continue,
+                // Nothing after a gap can complete a block before it, so the
+                // whole stack is final.
+                Some(top) if u32::from(cidr.first()) as u64 > u32::from(top.last()) as u64 + 1 => {
+                    self.ready = mem::replace(&mut self.stack, vec![cidr]).into_iter();
+                }
+                _ => {
+                    self.stack.push(cidr);
+                    while let [.., left, right] = self.stack[..] {
+                        match (left.parent(), right.parent()) {
+                            (Some(parent), Some(other)) if parent == other && left != right => {
+                                self.stack.truncate(self.stack.len() - 2);
+                                self.stack.push(parent);
+                            }
+                            _ => break,
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fcidr;
+
+    #[test]
+    fn it_works() {
+        let cidrs = [
+            "10.0.0.0/25",
+            "10.0.0.128/26",
+            "10.0.0.160/27",
+            "10.0.0.192/26",
+            "10.0.1.0/24",
+            "10.0.3.0/24",
+            "10.0.3.0/24",
+            "10.0.3.4/30",
+            "10.0.4.0/22",
+            "192.168.0.0/16",
+            "192.169.0.0/16",
+            "255.255.255.255/32",
+        ]
+        .map(|c| c.parse::<Cidr>().unwrap());
+        let aggregated = Aggregate::new(cidrs).collect::<Vec<_>>();
+        assert_eq!(
+            aggregated,
+            vec![
+                "10.0.0.0/23".parse().unwrap(),
+                "10.0.3.0/24".parse().unwrap(),
+                "10.0.4.0/22".parse().unwrap(),
+                "192.168.0.0/15".parse().unwrap(),
+                "255.255.255.255/32".parse().unwrap(),
+            ]
+        );
+        assert_eq!(aggregated, cidrs.into_iter().collect::<Fcidr>().to_vec());
+        assert_eq!(Aggregate::new(Vec::new()).next(), None);
+        let all = Aggregate::new(["0.0.0.0/1".parse().unwrap(), "128.0.0.0/1".parse().unwrap()]);
+        assert_eq!(all.collect::<Vec<_>>(), vec![Cidr::default()]);
+    }
+}
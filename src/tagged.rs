@@ -0,0 +1,117 @@
+use std::net::Ipv4Addr;
+
+use crate::{trie::Node, Cidr, Fcidr};
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TaggedFcidr<T = String> {
+    root: Node<Option<Vec<T>>>,
+}
+
+impl<T> TaggedFcidr<T>
+where
+    T: Clone + Ord,
+{
+    pub fn new() -> Self {
+        Self {
+            root: Node::default(),
+        }
+    }
+
+    pub fn difference(&mut self, cidr: Cidr) -> &mut Self {
+        self.root.update(cidr, &mut |tags| *tags = None);
+        self
+    }
+
+    pub fn union<I>(&mut self, cidr: Cidr, tags: I) -> &mut Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut new_tags = tags.into_iter().collect::<Vec<_>>();
+        new_tags.sort();
+        new_tags.dedup();
+        self.root.update(cidr, &mut |tags| {
+            let tags = tags.get_or_insert_with(Vec::new);
+            for tag in &new_tags {
+                if let Err(i) = tags.binary_search(tag) {
+                    tags.insert(i, tag.clone());
+                }
+            }
+        });
+        self
+    }
+
+    pub fn tags(&self, ip: Ipv4Addr) -> Option<&[T]> {
+        self.root.get(ip.into()).and_then(|tags| tags.as_deref())
+    }
+
+    pub fn is_superset(&self, cidr: Cidr) -> bool {
+        self.root.all(cidr, &Option::is_some)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Cidr, &[T])> + '_ {
+        self.root
+            .iter()
+            .filter_map(|(cidr, tags)| tags.as_deref().map(|tags| (cidr, tags)))
+    }
+}
+
+impl<T> Default for TaggedFcidr<T>
+where
+    T: Clone + Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<&TaggedFcidr<T>> for Fcidr
+where
+    T: Clone + Ord,
+{
+    fn from(value: &TaggedFcidr<T>) -> Self {
+        let mut fcidr = Self::default();
+        for (cidr, _) in value.iter() {
+            fcidr.union(cidr);
+        }
+        fcidr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut tagged = TaggedFcidr::new();
+        tagged
+            .union("10.0.0.0/8".parse().unwrap(), ["spamhaus".to_string()])
+            .union("10.1.0.0/16".parse().unwrap(), ["abuse.ch".to_string()])
+            .union("10.1.0.0/16".parse().unwrap(), ["spamhaus".to_string()])
+            .difference("10.0.0.0/16".parse().unwrap());
+        assert_eq!(
+            tagged.tags("10.1.2.3".parse().unwrap()),
+            Some(&["abuse.ch".to_string(), "spamhaus".to_string()][..])
+        );
+        assert_eq!(
+            tagged.tags("10.2.2.3".parse().unwrap()),
+            Some(&["spamhaus".to_string()][..])
+        );
+        assert_eq!(tagged.tags("10.0.2.3".parse().unwrap()), None);
+        assert!(tagged.is_superset("10.1.0.0/16".parse().unwrap()));
+        assert!(tagged.is_superset("10.128.0.0/9".parse().unwrap()));
+        assert!(!tagged.is_superset("10.0.0.0/15".parse().unwrap()));
+        let blocks = tagged
+            .iter()
+            .map(|(cidr, tags)| (cidr.to_string(), tags.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(blocks[0], ("10.1.0.0/16".to_string(), 2));
+        assert_eq!(blocks[1], ("10.2.0.0/15".to_string(), 1));
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        fcidr.difference("10.0.0.0/16".parse().unwrap());
+        assert_eq!(
+            Fcidr::from(&tagged).iter().collect::<Vec<_>>(),
+            fcidr.iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{Cidr, Fcidr, FcidrIter};
+
+#[derive(Debug)]
+#[must_use = "a transaction is aborted unless committed"]
+pub struct Transaction<'a> {
+    fcidr: &'a mut Fcidr,
+    pending: Fcidr,
+}
+
+impl<'a> Transaction<'a> {
+    pub(crate) fn new(fcidr: &'a mut Fcidr) -> Self {
+        let pending = fcidr.clone();
+        Self { fcidr, pending }
+    }
+
+    pub fn complement(&mut self) -> &mut Self {
+        self.pending.complement();
+        self
+    }
+
+    pub fn difference(&mut self, cidr: Cidr) -> &mut Self {
+        self.pending.difference(cidr);
+        self
+    }
+
+    pub fn union(&mut self, cidr: Cidr) -> &mut Self {
+        self.pending.union(cidr);
+        self
+    }
+
+    pub fn is_superset(&self, cidr: Cidr) -> bool {
+        self.pending.is_superset(cidr)
+    }
+
+    pub fn iter(&self) -> FcidrIter<'_> {
+        self.pending.iter()
+    }
+
+    pub fn commit(self) {
+        *self.fcidr = self.pending;
+    }
+
+    pub fn abort(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut fcidr = Fcidr::new("10.0.0.0/8".parse().unwrap());
+        let original = fcidr.iter().collect::<Vec<_>>();
+        let mut tx = fcidr.begin();
+        tx.difference("10.0.0.0/9".parse().unwrap())
+            .union("11.0.0.0/8".parse().unwrap());
+        assert!(tx.is_superset("11.0.0.0/8".parse().unwrap()));
+        tx.abort();
+        assert_eq!(fcidr.iter().collect::<Vec<_>>(), original);
+        let mut tx = fcidr.begin();
+        tx.difference("10.0.0.0/9".parse().unwrap())
+            .union("11.0.0.0/8".parse().unwrap());
+        tx.commit();
+        assert_eq!(
+            fcidr.iter().collect::<Vec<_>>(),
+            vec![
+                "10.128.0.0/9".parse().unwrap(),
+                "11.0.0.0/8".parse().unwrap()
+            ]
+        );
+    }
+}
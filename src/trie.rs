@@ -0,0 +1,311 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::{Cidr, Cidr6};
+
+/// A block of either address family, as keys of the trie.
+pub(crate) trait Block: Copy + Default {
+    type Addr: Copy + PartialOrd;
+
+    fn network(&self) -> Self::Addr;
+    fn prefix(&self) -> u8;
+    fn mid(&self) -> Self::Addr;
+    fn contains(&self, block: Self) -> bool;
+    fn split(&self) -> Option<[Self; 2]>;
+}
+
+impl Block for Cidr {
+    type Addr = Ipv4Addr;
+
+    fn network(&self) -> Self::Addr {
+        Cidr::network(self)
+    }
+
+    fn prefix(&self) -> u8 {
+        Cidr::prefix(self)
+    }
+
+    fn mid(&self) -> Self::Addr {
+        Cidr::mid(self)
+    }
+
+    fn contains(&self, block: Self) -> bool {
+        Cidr::contains(self, block)
+    }
+
+    fn split(&self) -> Option<[Self; 2]> {
+        Cidr::split(self)
+    }
+}
+
+impl Block for Cidr6 {
+    type Addr = Ipv6Addr;
+
+    fn network(&self) -> Self::Addr {
+        Cidr6::network(self)
+    }
+
+    fn prefix(&self) -> u8 {
+        Cidr6::prefix(self)
+    }
+
+    fn mid(&self) -> Self::Addr {
+        Cidr6::mid(self)
+    }
+
+    fn contains(&self, block: Self) -> bool {
+        Cidr6::contains(self, block)
+    }
+
+    fn split(&self) -> Option<[Self; 2]> {
+        Cidr6::split(self)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Value<T, C> {
+    Leaf(T),
+    Subnets(Box<[Node<T, C>; 2]>),
+}
+
+/// A binary trie over the address space whose leaves each carry a value,
+/// kept normalized such that sibling leaves never hold equal values.
+///
+/// [`Fcidr`](crate::Fcidr) keeps its own arena tree, whose nodes cache block
+/// and address counts and are shared between clones for its set operations.
+/// This one instead serves the structures needing an arbitrary value per leaf
+/// or IPv6 keys, which the arena's include/exclude leaves cannot carry.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct Node<T, C = Cidr> {
+    cidr: C,
+    value: Value<T, C>,
+}
+
+impl<T, C> Default for Node<T, C>
+where
+    T: Default,
+    C: Block,
+{
+    fn default() -> Self {
+        Self {
+            cidr: C::default(),
+            value: Value::Leaf(T::default()),
+        }
+    }
+}
+
+impl<T, C> Node<T, C>
+where
+    T: Clone + PartialEq,
+    C: Block,
+{
+    pub(crate) fn update<F>(&mut self, cidr: C, f: &mut F)
+    where
+        F: FnMut(&mut T),
+    {
+        if cidr.contains(self.cidr) {
+            match &mut self.value {
+                Value::Leaf(value) => f(value),
+                Value::Subnets(subnets) => {
+                    for subnet in subnets.iter_mut() {
+                        subnet.update(cidr, f);
+                    }
+                }
+            }
+        } else if self.cidr.contains(cidr) {
+            if let Value::Leaf(value) = &self.value {
+                let [left, right] = self.cidr.split().expect("split");
+                self.value = Value::Subnets(Box::new([
+                    Node {
+                        cidr: left,
+                        value: Value::Leaf(value.clone()),
+                    },
+                    Node {
+                        cidr: right,
+                        value: Value::Leaf(value.clone()),
+                    },
+                ]));
+            }
+            if let Value::Subnets(subnets) = &mut self.value {
+                let i = usize::from(cidr.network() >= self.cidr.mid());
+                subnets[i].update(cidr, f);
+            }
+        } else {
+            return;
+        }
+        if let Value::Subnets(subnets) = &self.value {
+            if let [Node {
+                value: Value::Leaf(left),
+                ..
+            }, Node {
+                value: Value::Leaf(right),
+                ..
+            }] = &**subnets
+            {
+                if left == right {
+                    self.value = Value::Leaf(left.clone());
+                }
+            }
+        }
+    }
+}
+
+impl<T, C> Node<T, C>
+where
+    C: Block,
+{
+    /// Returns the value shared by the whole of `cidr`, or `None` if it spans
+    /// leaves holding different values.
+    pub(crate) fn get(&self, cidr: C) -> Option<&T> {
+        let mut node = self;
+        loop {
+            match &node.value {
+                Value::Leaf(value) => return Some(value),
+                Value::Subnets(subnets) => {
+                    if cidr.prefix() <= node.cidr.prefix() {
+                        return None;
+                    }
+                    node = &subnets[usize::from(cidr.network() >= node.cidr.mid())];
+                }
+            }
+        }
+    }
+
+    /// Returns whether every leaf overlapping `cidr` satisfies `predicate`.
+    pub(crate) fn all<P>(&self, cidr: C, predicate: &P) -> bool
+    where
+        P: Fn(&T) -> bool,
+    {
+        if !self.cidr.contains(cidr) && !cidr.contains(self.cidr) {
+            return true;
+        }
+        match &self.value {
+            Value::Leaf(value) => predicate(value),
+            Value::Subnets(subnets) => subnets.iter().all(|subnet| subnet.all(cidr, predicate)),
+        }
+    }
+
+    pub(crate) fn iter(&self) -> Leaves<'_, T, C> {
+        Leaves { next: vec![self] }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Leaves<'a, T, C = Cidr> {
+    next: Vec<&'a Node<T, C>>,
+}
+
+impl<'a, T, C> Iterator for Leaves<'a, T, C>
+where
+    C: Copy,
+{
+    type Item = (C, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.next.pop() {
+            match &node.value {
+                Value::Leaf(value) => return Some((node.cidr, value)),
+                Value::Subnets(subnets) => self.next.extend(subnets.iter().rev()),
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(node: &Node<u8>) -> Vec<(String, u8)> {
+        node.iter()
+            .map(|(cidr, value)| (cidr.to_string(), *value))
+            .collect()
+    }
+
+    #[test]
+    fn insert() {
+        let mut node = Node::<u8>::default();
+        node.update("10.0.0.0/8".parse().unwrap(), &mut |value| *value = 1);
+        assert_eq!(
+            leaves(&node),
+            [
+                ("0.0.0.0/5".to_string(), 0),
+                ("8.0.0.0/7".to_string(), 0),
+                ("10.0.0.0/8".to_string(), 1),
+                ("11.0.0.0/8".to_string(), 0),
+                ("12.0.0.0/6".to_string(), 0),
+                ("16.0.0.0/4".to_string(), 0),
+                ("32.0.0.0/3".to_string(), 0),
+                ("64.0.0.0/2".to_string(), 0),
+                ("128.0.0.0/1".to_string(), 0),
+            ]
+        );
+        assert_eq!(node.get("10.1.0.0/16".parse().unwrap()), Some(&1));
+        assert_eq!(node.get("11.0.0.0/8".parse().unwrap()), Some(&0));
+        assert_eq!(node.get("10.0.0.0/7".parse().unwrap()), None);
+        assert!(node.all("10.0.0.0/8".parse().unwrap(), &|value| *value == 1));
+        assert!(!node.all("10.0.0.0/7".parse().unwrap(), &|value| *value == 1));
+        // Updating a block within a leaf to the value it has leaves it as is.
+        let before = node.clone();
+        node.update("10.1.0.0/16".parse().unwrap(), &mut |value| *value = 1);
+        assert_eq!(node, before);
+    }
+
+    #[test]
+    fn remove() {
+        let mut node = Node::<u8>::default();
+        node.update("10.0.0.0/8".parse().unwrap(), &mut |value| *value = 1);
+        node.update("10.0.0.0/8".parse().unwrap(), &mut |value| *value = 0);
+        assert_eq!(node, Node::default());
+        assert_eq!(leaves(&node), [("0.0.0.0/0".to_string(), 0)]);
+    }
+
+    #[test]
+    fn merge() {
+        let mut node = Node::<u8>::default();
+        node.update("10.0.0.0/9".parse().unwrap(), &mut |value| *value = 1);
+        node.update("10.128.0.0/10".parse().unwrap(), &mut |value| *value = 1);
+        node.update("10.192.0.0/10".parse().unwrap(), &mut |value| *value = 1);
+        let mut whole = Node::<u8>::default();
+        whole.update("10.0.0.0/8".parse().unwrap(), &mut |value| *value = 1);
+        // The siblings holding equal values merge back up into their parent.
+        assert_eq!(node, whole);
+        assert_eq!(node.get("10.0.0.0/8".parse().unwrap()), Some(&1));
+    }
+
+    #[test]
+    fn split() {
+        let mut node = Node::<u8>::default();
+        node.update("10.0.0.0/8".parse().unwrap(), &mut |value| *value = 1);
+        node.update("10.64.0.0/10".parse().unwrap(), &mut |value| *value = 0);
+        // Removing part of a leaf splits it down to the removed block.
+        assert_eq!(
+            leaves(&node)
+                .into_iter()
+                .filter(|(_, value)| *value == 1)
+                .collect::<Vec<_>>(),
+            [
+                ("10.0.0.0/10".to_string(), 1),
+                ("10.128.0.0/9".to_string(), 1),
+            ]
+        );
+        assert_eq!(node.get("10.64.0.0/10".parse().unwrap()), Some(&0));
+        assert_eq!(node.get("10.0.0.0/9".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn ipv6() {
+        let mut node = Node::<u8, Cidr6>::default();
+        node.update("2001:db8::/32".parse().unwrap(), &mut |value| *value += 1);
+        node.update("2001:db8::/48".parse().unwrap(), &mut |value| *value += 1);
+        assert_eq!(node.get("2001:db8::/64".parse().unwrap()), Some(&2));
+        assert_eq!(node.get("2001:db8:1::/48".parse().unwrap()), Some(&1));
+        assert_eq!(node.get("2001:db8::/32".parse().unwrap()), None);
+        assert_eq!(
+            node.iter()
+                .filter(|(_, value)| **value == 2)
+                .map(|(cidr, _)| cidr.to_string())
+                .collect::<Vec<_>>(),
+            ["2001:db8::/48"]
+        );
+    }
+}
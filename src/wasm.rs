@@ -0,0 +1,57 @@
+#![cfg(feature = "wasm")]
+#![cfg_attr(doc_cfg, doc(cfg(feature = "wasm")))]
+
+use wasm_bindgen::prelude::*;
+
+use crate::{Cidr, Fcidr};
+
+fn parse(cidr: &str) -> Result<Cidr, JsError> {
+    cidr.trim()
+        .parse()
+        .map_err(|e: crate::Error| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen(js_name = Fcidr)]
+#[derive(Debug, Default)]
+pub struct WasmFcidr(Fcidr);
+
+#[wasm_bindgen(js_class = Fcidr)]
+impl WasmFcidr {
+    #[wasm_bindgen(constructor)]
+    pub fn new(cidr: Option<String>) -> Result<WasmFcidr, JsError> {
+        match cidr {
+            Some(cidr) => Ok(Self(Fcidr::new(parse(&cidr)?))),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(cidrs: &str) -> Result<WasmFcidr, JsError> {
+        let mut fcidr = Fcidr::default();
+        for cidr in cidrs.split_whitespace() {
+            fcidr.union(parse(cidr)?);
+        }
+        Ok(Self(fcidr))
+    }
+
+    pub fn complement(&mut self) {
+        self.0.complement();
+    }
+
+    pub fn difference(&mut self, cidr: &str) -> Result<(), JsError> {
+        self.0.difference(parse(cidr)?);
+        Ok(())
+    }
+
+    pub fn union(&mut self, cidr: &str) -> Result<(), JsError> {
+        self.0.union(parse(cidr)?);
+        Ok(())
+    }
+
+    pub fn contains(&self, cidr: &str) -> Result<bool, JsError> {
+        Ok(self.0.is_superset(parse(cidr)?))
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        self.0.iter().map(|cidr| cidr.to_string()).collect()
+    }
+}
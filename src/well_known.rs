@@ -0,0 +1,108 @@
+use crate::Fcidr;
+
+// https://www.iana.org/assignments/iana-ipv4-special-registry
+const SPECIAL_USE: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.0.2.0/24",
+    "192.31.196.0/24",
+    "192.52.193.0/24",
+    "192.88.99.0/24",
+    "192.168.0.0/16",
+    "192.175.48.0/24",
+    "198.18.0.0/15",
+    "198.51.100.0/24",
+    "203.0.113.0/24",
+    "240.0.0.0/4",
+    "255.255.255.255/32",
+];
+
+// The Team Cymru bogon prefixes, excluding the unallocated ones that change
+// over time.
+const BOGONS: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.0.2.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "198.51.100.0/24",
+    "203.0.113.0/24",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+];
+
+fn from_static(cidrs: &[&str]) -> Fcidr {
+    cidrs.iter().map(|cidr| cidr.parse().unwrap()).collect()
+}
+
+impl Fcidr {
+    /// Private-use networks (RFC 1918).
+    pub fn rfc1918() -> Self {
+        from_static(&["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"])
+    }
+
+    /// Every block of the IANA IPv4 Special-Purpose Address Registry.
+    pub fn special_use() -> Self {
+        from_static(SPECIAL_USE)
+    }
+
+    /// The RFC 6890 special-purpose registry, same as [`Fcidr::special_use`].
+    pub fn special_purpose() -> Self {
+        Self::special_use()
+    }
+
+    /// Addresses that should never appear on the public internet, for
+    /// filtering announcements and spoofed traffic.
+    pub fn bogons() -> Self {
+        from_static(BOGONS)
+    }
+
+    /// Shared address space for carrier-grade NAT (RFC 6598).
+    pub fn cgn() -> Self {
+        from_static(&["100.64.0.0/10"])
+    }
+
+    pub fn multicast() -> Self {
+        from_static(&["224.0.0.0/4"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let mut allow = Fcidr::new("0.0.0.0/1".parse().unwrap());
+        allow.difference_set(&Fcidr::special_use());
+        assert!(!allow.is_superset("10.1.0.0/16".parse().unwrap()));
+        assert!(!allow.is_superset("127.0.0.1/32".parse().unwrap()));
+        assert!(allow.is_superset("8.8.8.8/32".parse().unwrap()));
+        for fcidr in [Fcidr::rfc1918(), Fcidr::cgn()] {
+            for cidr in &fcidr {
+                assert!(Fcidr::special_use().is_superset(cidr));
+            }
+        }
+        assert!(!Fcidr::special_use().is_superset("224.0.0.0/4".parse().unwrap()));
+        assert!(Fcidr::multicast().is_superset("239.255.255.250/32".parse().unwrap()));
+        assert!(Fcidr::bogons().is_superset("224.0.0.0/3".parse().unwrap()));
+        for cidr in &Fcidr::rfc1918() {
+            assert!(Fcidr::bogons().is_superset(cidr));
+        }
+        assert!(!Fcidr::bogons().is_superset("192.88.99.0/24".parse().unwrap()));
+        assert_eq!(
+            Fcidr::special_purpose().iter().collect::<Vec<_>>(),
+            Fcidr::special_use().iter().collect::<Vec<_>>()
+        );
+    }
+}